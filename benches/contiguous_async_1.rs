@@ -176,8 +176,109 @@ pub fn receive_full(c: &mut Criterion) {
     );
 }
 
+// These move a batch of messages through at a time. With nobody
+// waiting, neither side should pay for waking the other.
 
+const BATCH: i32 = 64;
 
+type Channel<C = u32> = (Sender<'static, 'static, i32, DefaultNotify, C>,
+                          Receiver<'static, 'static, i32, DefaultNotify, C>);
+
+// Fills the channel `new` makes with `now()`, then empties it.
+fn now<C: Capacity>(c: &mut Criterion, name: &str, new: impl Fn() -> Channel<C>) {
+    let (mut s, mut r) = new();
+    let n = s.capacity() as i32;
+    let mut group = c.benchmark_group("contiguous_async_1/throughput");
+    group.throughput(Throughput::Elements(n as u64));
+    group.bench_function(name, |b| b.iter(|| {
+        for i in 0..n { s.send(i).now().unwrap(); }
+        for _ in 0..n { black_box(r.receive().now().unwrap()); }
+    }));
+    group.finish();
+}
+
+// Sends 100 channels' worth through the channel `new` makes, from
+// another thread, yielding whenever one side has to wait.
+fn threads(c: &mut Criterion, name: &str, new: impl Fn() -> Channel) {
+    let mut group = c.benchmark_group("contiguous_async_1/throughput");
+    group.throughput(Throughput::Elements(BATCH as u64 * 100));
+    group.bench_function(name, |b| b.iter_batched(
+        &new,
+        |(mut s, mut r)| {
+            let t = std::thread::spawn(move || {
+                for i in 0..BATCH * 100 {
+                    let mut value = i;
+                    while let Err(e) = s.send(value).now() {
+                        value = e.value;
+                        std::thread::yield_now();
+                    }
+                }
+            });
+            let mut count = 0;
+            while count < BATCH * 100 {
+                match r.receive().now() {
+                    Ok(Some(v)) => { black_box(v); count += 1; }
+                    _ => std::thread::yield_now(),
+                }
+            }
+            t.join().unwrap();
+        },
+        BatchSize::SmallInput
+    ));
+    group.finish();
+}
+
+pub fn throughput_now(c: &mut Criterion) {
+    now(c, "now", || spsc(BATCH as u32));
+}
+
+// As above, but with each side's position on its own cache line.
+pub fn throughput_now_padded(c: &mut Criterion) {
+    now(c, "now_padded", || spsc_padded(BATCH as u32));
+}
+
+// As above, but with a capacity which is not a power of two.
+pub fn throughput_now_odd(c: &mut Criterion) {
+    now(c, "now_odd", || spsc(BATCH as u32 - 1));
+}
+
+// As above, but with a capacity we promise is a power of two.
+pub fn throughput_now_pow2(c: &mut Criterion) {
+    now(c, "now_pow2", || spsc_pow2(BATCH as u32));
+}
+
+// As above, but with the capacity fixed at compile time.
+pub fn throughput_now_const(c: &mut Criterion) {
+    now(c, "now_const", spsc_const::<i32, { BATCH as u32 }>);
+}
+
+pub fn throughput_async(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contiguous_async_1/throughput");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    group.bench_function("async", |b| {
+        let (mut s, mut r) = spsc::<i32>(BATCH as u32);
+        b.iter(|| {
+            for i in 0..BATCH {
+                dummy!(s2: s.send(i));
+                assert!(s2.poll().is_ready());
+            }
+            for _ in 0..BATCH {
+                dummy!(r2: r.receive());
+                assert!(r2.poll().is_ready());
+            }
+        })
+    });
+    group.finish();
+}
+
+pub fn throughput_threads(c: &mut Criterion) {
+    threads(c, "threads", || spsc(BATCH as u32));
+}
+
+// As above, but with each side's position on its own cache line.
+pub fn throughput_threads_padded(c: &mut Criterion) {
+    threads(c, "threads_padded", || spsc_padded(BATCH as u32));
+}
 
 criterion_group!(
     benches,
@@ -194,5 +295,13 @@ criterion_group!(
     receive_closed,
     receive_empty,
     receive_full,
+    throughput_now,
+    throughput_now_padded,
+    throughput_now_odd,
+    throughput_now_pow2,
+    throughput_now_const,
+    throughput_async,
+    throughput_threads,
+    throughput_threads_padded,
 );
 criterion_main!(benches);
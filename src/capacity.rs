@@ -0,0 +1,108 @@
+//! What a channel's capacity is stored as.
+//!
+//! Channels from [`spsc`](crate::spsc) store their capacity as a
+//! `Half`, while those from [`spsc_const`](crate::spsc_const) carry it
+//! in their type as [`Const`], so the index arithmetic on every send
+//! and receive can be worked out by the compiler:
+//!
+//! ```
+//! use async_spsc::{spsc_const, Const, Sender};
+//!
+//! let (mut sender, mut receiver) = spsc_const::<i32, 16>();
+//! let _: &Sender<i32, _, Const<16>> = &sender;
+//! sender.send(42).now().unwrap();
+//! assert_eq!(Ok(Some(42)), receiver.receive().now());
+//! ```
+//!
+//! When `N` is a power of two, that arithmetic comes down to a mask.
+//! Channels from [`spsc_pow2`](crate::spsc_pow2) get the mask without
+//! fixing the capacity at compile time: [`Pow2`] stores its logarithm,
+//! so the compiler can still see it is a power of two.
+//!
+//! Channels from [`spsc_compact`](crate::spsc_compact) store it as
+//! whichever of `u8`, `u16` or `u32` it is given, and pack their state
+//! into an atomic twice as wide. A `u8` capacity (of at most 63) packs
+//! it into an `AtomicU16`. Only the shared header gets smaller, not the
+//! Sender and Receiver:
+//!
+//! ```
+//! use async_spsc::spsc_compact;
+//!
+//! let (mut sender, mut receiver) = spsc_compact::<i32, u8>(4);
+//! sender.send(42).now().unwrap();
+//! assert_eq!(Ok(Some(42)), receiver.receive().now());
+//! ```
+use crate::{AtomicState, AtomicWord, Half};
+use core::convert::TryFrom;
+use crate::atomic::{AtomicU16, AtomicU32};
+#[cfg(any(target_pointer_width="64", all(feature="wide", target_pointer_width="32", target_has_atomic="64")))]
+use crate::atomic::AtomicU64;
+
+/// The capacity of a channel, in messages.
+pub trait Capacity: Copy + Send + Sync + 'static {
+    /// The atomic the state is packed into.
+    type Atomic: AtomicState;
+    fn get(self) -> Half;
+    /// A capacity of `cap` messages instead, for channels which are
+    /// resized. Panics if it cannot be stored.
+    fn resize(self, cap: Half) -> Self {
+        assert_eq!(cap, self.get(), "this capacity cannot be resized");
+        self
+    }
+}
+
+// The casts and conversions are not needed for the widest.
+#[allow(clippy::unnecessary_cast, clippy::unnecessary_fallible_conversions, clippy::useless_conversion)]
+impl Capacity for u8 {
+    type Atomic = AtomicU16;
+    #[inline(always)]
+    fn get(self) -> Half { self as Half }
+    fn resize(self, cap: Half) -> Self { u8::try_from(cap).expect("capacity too large") }
+}
+
+#[cfg(not(target_pointer_width="16"))]
+#[allow(clippy::unnecessary_cast, clippy::unnecessary_fallible_conversions, clippy::useless_conversion)]
+impl Capacity for u16 {
+    type Atomic = AtomicU32;
+    #[inline(always)]
+    fn get(self) -> Half { self as Half }
+    fn resize(self, cap: Half) -> Self { u16::try_from(cap).expect("capacity too large") }
+}
+
+#[cfg(any(target_pointer_width="64", all(feature="wide", target_pointer_width="32", target_has_atomic="64")))]
+impl Capacity for u32 {
+    type Atomic = AtomicU64;
+    #[inline(always)]
+    fn get(self) -> Half { self }
+    fn resize(self, cap: Half) -> Self { cap }
+}
+
+/// A capacity of `N` messages, known at compile time.
+#[derive(Clone,Copy,Debug,Default,Eq,Hash,PartialEq)]
+pub struct Const<const N: Half>;
+
+impl<const N: Half> Capacity for Const<N> {
+    type Atomic = AtomicWord;
+    #[inline(always)]
+    fn get(self) -> Half { N }
+}
+
+/// A capacity which is a power of two, stored as its logarithm so the
+/// index arithmetic is a mask rather than a division.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct Pow2(u8);
+
+impl Pow2 {
+    /// Note: will panic if `cap` is not a power of two.
+    pub fn new(cap: Half) -> Self {
+        assert!(cap.is_power_of_two(), "capacity must be a power of two");
+        Pow2(cap.trailing_zeros() as u8)
+    }
+}
+
+impl Capacity for Pow2 {
+    type Atomic = AtomicWord;
+    #[inline(always)]
+    fn get(self) -> Half { 1 << self.0 }
+    fn resize(self, cap: Half) -> Self { Pow2::new(cap) }
+}
@@ -0,0 +1,136 @@
+//! Readiness notification through Linux eventfds, so a channel can be
+//! polled alongside sockets by a reactor that isn't a futures
+//! executor (e.g. mio).
+//!
+//! A channel created with [`spsc_fd`] (or with an [`EventFds`] passed
+//! to [`spsc_with`]) has an eventfd for each side, exposed through
+//! [`AsRawFd`] and [`mio::event::Source`]. The Receiver's becomes
+//! readable when the channel goes from empty to non-empty and the
+//! Sender's when it goes from full to not full (or when they reach the
+//! minimum batch or space, if one has been set).
+//! Each also becomes readable when the other side closes. Once
+//! readable, use `now()` until it would block.
+//!
+//! ```
+//! use async_spsc::spsc_fd;
+//! use std::os::unix::io::AsRawFd;
+//!
+//! let (mut sender, mut receiver) = spsc_fd::<i32>(2).unwrap();
+//! let mut poll = libc::pollfd { fd: receiver.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+//! assert_eq!(0, unsafe { libc::poll(&mut poll, 1, 0) }); // nothing yet
+//! sender.send(42).now().unwrap();
+//! assert_eq!(1, unsafe { libc::poll(&mut poll, 1, 0) });
+//! assert_eq!(receiver.receive().now(), Ok(Some(42)));
+//! assert_eq!(receiver.receive().now(), Ok(None));
+//! assert_eq!(0, unsafe { libc::poll(&mut poll, 1, 0) }); // drained
+//! ```
+//!
+//! Only channels with [`EventFds`] have them, so other channels can't
+//! be registered by mistake:
+//!
+//! ```compile_fail
+//! use async_spsc::spsc;
+//! use std::os::unix::io::AsRawFd;
+//!
+//! let (_sender, receiver) = spsc::<i32>(2);
+//! receiver.as_raw_fd();
+//! ```
+//!
+//! The eventfds are in addition to the usual wakers, so both sides may
+//! still be awaited. `wait()` blocks in `poll(2)` on the eventfd.
+use crate::*;
+use std::io;
+use std::os::unix::io::RawFd;
+
+#[cfg(doc)]
+use std::os::unix::io::AsRawFd;
+
+/// Creates a new heap-backed channel that can store up to `capacity`
+/// in-flight messages at a time, with an eventfd for each side.
+///
+/// Note: will panic if capacity is 0 or too large to represent.
+#[allow(clippy::type_complexity)]
+pub fn spsc_fd<T>(capacity: Half)
+                  -> io::Result<(Sender<'static, 'static, T, EventFds>, Receiver<'static, 'static, T, EventFds>)> {
+    Ok(spsc_with(capacity, EventFds::new()?))
+}
+
+/// A [`Notify`] which makes an eventfd readable when its side may
+/// make progress, as well as waking any futures (see [`Wakers`]).
+#[derive(Debug)]
+pub struct EventFds {
+    sender:   EventFd,
+    receiver: EventFd,
+    #[cfg(feature="async")]
+    wakers:   Wakers,
+}
+
+impl EventFds {
+    pub fn new() -> io::Result<Self> {
+        Ok(EventFds {
+            sender:   EventFd::new()?,
+            receiver: EventFd::new()?,
+            #[cfg(feature="async")]
+            wakers:   Wakers::default(),
+        })
+    }
+}
+
+impl Notify for EventFds {
+    fn notify(&self, side: Side, was_idle: bool) {
+        // If they weren't idle, it's still readable.
+        if was_idle { signal(self.fd(side)); }
+        #[cfg(feature="async")]
+        self.wakers.notify(side, was_idle);
+    }
+
+    fn idle(&self, side: Side) -> bool {
+        let mut buf = 0u64;
+        unsafe { libc::read(self.fd(side), (&mut buf as *mut u64).cast(), 8) };
+        true
+    }
+
+    #[cfg(feature="async")]
+    fn register(&self, side: Side, waker: &Waker) { self.wakers.register(side, waker) }
+
+    #[cfg(feature="async")]
+    fn registered(&self, side: Side) -> bool { self.wakers.registered(side) }
+
+    #[cfg(feature="async")]
+    fn unregister(&self, side: Side) { self.wakers.unregister(side) }
+
+    /// The eventfd for `side`.
+    fn fd(&self, side: Side) -> RawFd {
+        match side {
+            Side::Sender => self.sender.0,
+            Side::Receiver => self.receiver.0,
+        }
+    }
+
+    fn wait(&self, side: Side, idle: &dyn Fn() -> bool) {
+        let mut poll = libc::pollfd { fd: self.fd(side), events: libc::POLLIN, revents: 0 };
+        if idle() { unsafe { libc::poll(&mut poll, 1, -1) }; }
+    }
+}
+
+#[derive(Debug)]
+struct EventFd(RawFd);
+
+impl EventFd {
+    fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if fd < 0 { return Err(io::Error::last_os_error()); }
+        Ok(EventFd(fd))
+    }
+}
+
+impl Drop for EventFd {
+    fn drop(&mut self) { unsafe { libc::close(self.0) }; }
+}
+
+/// Makes the eventfd readable.
+#[inline(always)]
+fn signal(fd: RawFd) {
+    let one = 1u64;
+    unsafe { libc::write(fd, (&one as *const u64).cast(), 8) };
+}
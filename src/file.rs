@@ -0,0 +1,75 @@
+//! Persistent channels backed by a memory-mapped file (Linux only).
+//!
+//! The file holds the same header, state word and ring buffer as a
+//! [`shm`](crate::shm) region, so messages that have been sent but
+//! not yet received survive the process exiting or crashing. After a
+//! restart, [`recover`] picks up from the persisted front and back.
+//!
+//! ```no_run
+//! use async_spsc::file;
+//! use core::ffi::CStr;
+//!
+//! let path = CStr::from_bytes_with_nul(b"/var/lib/ingest/queue\0").unwrap();
+//! let (mut sender, receiver) = file::create::<u64>(path, 1024).unwrap();
+//! sender.send(42).now().unwrap();
+//! drop((sender, receiver));
+//! // Later, possibly after a crash...
+//! let (sender, mut receiver) = unsafe { file::recover::<u64>(path) }.unwrap();
+//! assert_eq!(receiver.receive().now(), Ok(Some(42)));
+//! ```
+//!
+//! As with shm, only `Copy` types may be sent. Both sides may be
+//! used from different processes or threads just like a shm channel,
+//! and blocking waits and crash detection work the same way.
+//!
+//! Note: changes reach the file when the operating system writes the
+//! pages back. That happens even if our process crashes, but we do
+//! not force it, so a crash of the whole machine may lose messages.
+use crate::*;
+use crate::shm::{Shm, ShmError};
+use core::ffi::CStr;
+use libc::c_int;
+
+/// Creates a new file at `path` holding a channel with capacity for
+/// `capacity` messages. Fails if the file already exists.
+///
+/// Note: will panic if capacity is 0 or too large to represent.
+pub fn create<T: Copy>(path: &CStr, capacity: Half)
+                       -> Result<(Sender<'static, 'static, T>, Receiver<'static, 'static, T>), ShmError> {
+    let flags = libc::O_RDWR | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC;
+    let fd = unsafe { libc::open(path.as_ptr(), flags, 0o600) };
+    if fd < 0 { return Err(ShmError::last()); }
+    let ret = unsafe { Shm::init(fd, capacity) }.and_then(|shm| split(shm, fd));
+    unsafe { libc::close(fd) };
+    if ret.is_err() { unsafe { libc::unlink(path.as_ptr()) }; }
+    ret
+}
+
+/// Reopens a file created by [`create`], resuming from the persisted
+/// state. Messages which were sent but not received will be received
+/// again, in order.
+///
+/// ## Safety
+///
+/// * The file must have been created for this `T`. We can only check
+///   that the size and alignment match.
+/// * The file must not still be in use by another channel.
+pub unsafe fn recover<T: Copy>(path: &CStr)
+                               -> Result<(Sender<'static, 'static, T>, Receiver<'static, 'static, T>), ShmError> {
+    let fd = libc::open(path.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC);
+    if fd < 0 { return Err(ShmError::last()); }
+    let ret = Shm::from_fd(fd).and_then(|shm| {
+        // Whoever had it before closed (or crashed) without us.
+        shm.reset();
+        split(shm, fd)
+    });
+    libc::close(fd);
+    ret
+}
+
+/// Maps the file a second time so each side has its own mapping.
+fn split<T: Copy>(shm: Shm<T>, fd: c_int)
+                  -> Result<(Sender<'static, 'static, T>, Receiver<'static, 'static, T>), ShmError> {
+    let peer = unsafe { Shm::from_fd(fd) }?;
+    Ok((shm.sender()?, peer.receiver()?))
+}
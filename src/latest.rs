@@ -0,0 +1,282 @@
+//! A channel which only keeps the latest value.
+//!
+//! For config, positions and the like, where only the most recent
+//! value matters, [`latest`](crate::latest()) makes a channel whose
+//! Sender replaces any value the Receiver has yet to take. The
+//! Receiver gets the newest, or nothing if there has been no change
+//! since it last looked:
+//!
+//! ```
+//! use async_spsc::latest;
+//!
+//! let (mut sender, mut receiver) = latest::<i32>();
+//! for i in 0..5 { sender.send(i).unwrap(); }
+//! assert_eq!(Ok(Some(4)), receiver.receive().now());
+//! assert_eq!(Ok(None), receiver.receive().now());
+//! ```
+//!
+//! It is a triple buffer built on the usual atomics: each side owns a
+//! slot and the third is passed between them. The state packs which
+//! slot is in the middle and whether it holds a value the Receiver has
+//! yet to take in the front half, which is the Receiver's in the back
+//! half and a close flag for each as usual. Sending swaps the Sender's
+//! slot into the middle and receiving swaps it out to the Receiver, so
+//! neither side ever waits for the other to finish.
+use crate::*;
+use core::cell::Cell;
+
+// The index of the middle slot and whether it is fresh, in the front,
+// and the index of the Receiver's slot, in the back. The Sender's is
+// the one left.
+const MIDDLE: Word = 0b11;
+const FRESH:  Word = 0b100;
+const READER: Word = MIDDLE << BITS;
+
+#[inline(always)]
+fn middle(state: State) -> usize { (state.0 & MIDDLE) as usize }
+
+#[inline(always)]
+fn reader(state: State) -> usize { ((state.0 & READER) >> BITS) as usize }
+
+#[inline(always)]
+fn writer(state: State) -> usize { 3 - middle(state) - reader(state) }
+
+#[inline(always)]
+fn is_fresh(state: State) -> bool { (state.0 & FRESH) != 0 }
+
+/// Creates the page for a new channel, the Sender starting out with
+/// the first slot and the Receiver the last.
+pub(crate) fn alloc_latest<T, N: Notify>(notify: N) -> (LatestSender<T, N>, LatestReceiver<T, N>) {
+    let state = State(1 | (2 << BITS));
+    let atomics = Atomics { state: AtomicWord::new(state.0), marks: AtomicWord::new(0), notify };
+    let page = PageRef::new(atomics, 3);
+    (LatestSender { page: Some(page) },
+     LatestReceiver { page: Some(page), state: Cell::new(state), wait: WaitStrategy::Park })
+}
+
+/// Closes `side`, cleaning up if the peer already has.
+///
+/// Safe only if `side` has no further use for the page.
+unsafe fn close<T, N: Notify>(page: PageRef<Atomics<N>, T>, side: Side, flag: Word) {
+    let atomics = page.header();
+    let prev = atomics.state.xor_state(flag, Ordering::AcqRel);
+    if !prev.is_closed() {
+        // The Receiver may be waiting for a value it will now never get.
+        if side == Side::Sender { atomics.notify.notify(Side::Receiver, !is_fresh(prev)); }
+        return;
+    }
+    // Each side's own slot is empty between operations, so only a
+    // value left in the middle is in flight.
+    if is_fresh(prev) { drop_in_place(page.data().add(middle(prev)).cast::<T>()); }
+    PageRef::drop(page);
+}
+
+/// The sending side of a [`latest`](crate::latest()) channel.
+pub struct LatestSender<T, N: Notify = DefaultNotify> {
+    page: Option<PageRef<Atomics<N>, T>>,
+}
+
+impl<T, N: Notify> LatestSender<T, N> {
+    /// Replaces the value in the channel with `value`, dropping the
+    /// last one if the Receiver has yet to take it. Fails only if the
+    /// Receiver has closed.
+    pub fn send(&mut self, value: T) -> Result<(), SendError<T>> {
+        let closed = |value| Err(SendError { kind: SendErrorKind::Closed, value });
+        let page = match self.page {
+            Some(page) => page,
+            None => return closed(value),
+        };
+        let atomics = unsafe { page.header() };
+        let mut seen = atomics.state.load_state(Ordering::Acquire);
+        // The Receiver only ever swaps the middle with its own, so
+        // ours stays put until we swap it ourselves.
+        let slot = writer(seen);
+        unsafe { page.data().add(slot).write(MaybeUninit::new(value)) };
+        loop {
+            if seen.is_closed() {
+                return closed(unsafe { page.data().add(slot).read().assume_init() });
+            }
+            let next = State((seen.0 & !MIDDLE) | slot as Word | FRESH);
+            match atomics.state.swap_state(seen, next, Ordering::AcqRel) {
+                Ok(_) => break,
+                Err(s) => seen = s,
+            }
+        }
+        // The old middle is ours now. If the Receiver never took its
+        // value, it has been replaced and we must drop it. Otherwise
+        // it may be waiting for this one.
+        if is_fresh(seen) {
+            unsafe { drop_in_place(page.data().add(middle(seen)).cast::<T>()) };
+        } else {
+            atomics.notify.notify(Side::Receiver, true);
+        }
+        Ok(())
+    }
+}
+
+impl<T, N: Notify> Drop for LatestSender<T, N> {
+    fn drop(&mut self) {
+        if let Some(page) = self.page.take() { unsafe { close(page, Side::Sender, S_CLOSE) } }
+    }
+}
+
+unsafe impl<T: Send, N: Notify> Send for LatestSender<T, N> {}
+unsafe impl<T: Send, N: Notify> Sync for LatestSender<T, N> {}
+
+/// The receiving side of a [`latest`](crate::latest()) channel.
+pub struct LatestReceiver<T, N: Notify = DefaultNotify> {
+    page:  Option<PageRef<Atomics<N>, T>>,
+    state: Cell<State>,
+    wait:  WaitStrategy,
+}
+
+impl<T, N: Notify> LatestReceiver<T, N> {
+    /// Sets what we do when we find no new value. See [`WaitStrategy`].
+    pub fn set_wait_strategy(&mut self, wait: WaitStrategy) { self.wait = wait; }
+
+    /// Returns a disposable object which can receive the latest value
+    /// either synchronously via [`LatestReceiving::now`] or
+    /// asynchronously via the [`core::future::Future`] instance.
+    pub fn receive(&mut self) -> LatestReceiving<'_, T, N> {
+        LatestReceiving {
+            receiver: Some(self),
+            #[cfg(feature="async")]
+            waker:    None,
+        }
+    }
+
+    /// Loads the state, letting the Notify know if we find nothing new
+    /// (see [`Notify::idle`]).
+    #[inline(always)]
+    fn refresh(&self, page: PageRef<Atomics<N>, T>) -> State {
+        let atomics = unsafe { page.header() };
+        let ready = |state: State| state.is_closed() || is_fresh(state);
+        let mut state = atomics.state.load_state(Ordering::Acquire);
+        if !ready(state) && atomics.notify.idle(Side::Receiver) {
+            // The Sender may have sent before we forgot.
+            state = atomics.state.load_state(Ordering::Acquire);
+            if ready(state) { atomics.notify.notify(Side::Receiver, true); }
+        }
+        self.state.set(state);
+        state
+    }
+
+    /// Takes the value in the middle, if it is one we have yet to take.
+    fn take(&self, page: PageRef<Atomics<N>, T>) -> Result<Option<T>, Closed> {
+        let atomics = unsafe { page.header() };
+        let mut seen = self.refresh(page);
+        loop {
+            if !is_fresh(seen) {
+                return if seen.is_closed() { Err(Closed) } else { Ok(None) };
+            }
+            let (m, r) = (middle(seen) as Word, reader(seen) as Word);
+            let next = State((seen.0 & !(MIDDLE | FRESH | READER)) | r | (m << BITS));
+            match atomics.state.swap_state(seen, next, Ordering::AcqRel) {
+                Ok(_) => {
+                    self.state.set(next);
+                    // It is our slot now, and it is empty again once
+                    // we have read it.
+                    return Ok(Some(unsafe { page.data().add(m as usize).read().assume_init() }));
+                }
+                Err(s) => seen = s,
+            }
+        }
+    }
+}
+
+impl<T, N: Notify> Drop for LatestReceiver<T, N> {
+    fn drop(&mut self) {
+        if let Some(page) = self.page.take() {
+            // Don't leave our waker in its slot.
+            #[cfg(feature="async")]
+            unsafe { page.header() }.notify.unregister(Side::Receiver);
+            unsafe { close(page, Side::Receiver, R_CLOSE) }
+        }
+    }
+}
+
+unsafe impl<T: Send, N: Notify> Send for LatestReceiver<T, N> {}
+unsafe impl<T: Send, N: Notify> Sync for LatestReceiver<T, N> {}
+
+/// A single receive from a [`LatestReceiver`], which can be performed
+/// synchronously (with [`LatestReceiving::now`]) or asynchronously
+/// (with the [`core::future::Future`] instance, which waits for a
+/// change).
+pub struct LatestReceiving<'a, T, N: Notify = DefaultNotify> {
+    receiver: Option<&'a mut LatestReceiver<T, N>>,
+    // The waker we last registered, if any.
+    #[cfg(feature="async")]
+    waker:    Option<Waker>,
+}
+
+impl<'a, T, N: Notify> LatestReceiving<'a, T, N> {
+    /// Takes the latest value, if it has changed since we last took
+    /// one. Fails once the Sender has closed and there is no such
+    /// value.
+    pub fn now(mut self) -> Result<Option<T>, Closed> {
+        let receiver = self.receiver.take().unwrap();
+        receiver.page.map_or(Err(Closed), |page| receiver.take(page))
+    }
+
+    /// Takes the latest value, blocking the current thread until it
+    /// changes or the Sender closes.
+    ///
+    /// Note: this only truly blocks for channels whose [`Notify`] can
+    /// wait (e.g. `Park`). Others will spin.
+    pub fn wait(mut self) -> Result<T, Closed> {
+        let receiver = self.receiver.take().unwrap();
+        let page = receiver.page.ok_or(Closed)?;
+        let atomics = unsafe { page.header() };
+        loop {
+            if let Some(value) = receiver.take(page)? { return Ok(value); }
+            let seen = receiver.state.get();
+            let moved = || atomics.state.load_state(Ordering::Acquire) != seen;
+            if !receiver.wait.spin(moved) { atomics.notify.wait(Side::Receiver, &|| !moved()); }
+        }
+    }
+}
+
+#[cfg(feature="async")]
+impl<'a, T, N: Notify> Future for LatestReceiving<'a, T, N> {
+    type Output = Result<T, Closed>;
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let receiver = this.receiver.take().unwrap();
+        let page = match receiver.page {
+            Some(page) => page,
+            None => return Poll::Ready(Err(Closed)),
+        };
+        let atomics = unsafe { page.header() };
+        if let Some(ret) = receiver.take(page).transpose() { return Poll::Ready(ret); }
+        // Maybe the Sender is only just behind us.
+        let ready = || {
+            let state = atomics.state.load_state(Ordering::Acquire);
+            state.is_closed() || is_fresh(state)
+        };
+        if receiver.wait.spin(ready) {
+            if let Some(ret) = receiver.take(page).transpose() { return Poll::Ready(ret); }
+        }
+        // Go into hibernation, unless we are still registered.
+        let notify = &atomics.notify;
+        let same = this.waker.as_ref().is_some_and(|last| last.will_wake(ctx.waker()));
+        if !(same && notify.registered(Side::Receiver)) {
+            notify.register(Side::Receiver, ctx.waker());
+            if !same { this.waker = Some(ctx.waker().clone()); }
+        }
+        // They may have sent before we registered.
+        if let Some(ret) = receiver.take(page).transpose() { return Poll::Ready(ret); }
+        this.receiver.replace(receiver);
+        Poll::Pending
+    }
+}
+
+#[cfg(feature="async")]
+impl<'a, T, N: Notify> Drop for LatestReceiving<'a, T, N> {
+    fn drop(&mut self) {
+        if self.waker.is_some() {
+            if let Some(page) = self.receiver.as_ref().and_then(|r| r.page) {
+                unsafe { page.header() }.notify.unregister(Side::Receiver);
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Laying a channel out to avoid false sharing.
+//!
+//! By default, both sides share a single atomic word holding both of
+//! their positions, next to the wakeup machinery. That is as small as a
+//! channel can be, which is the right choice when there are many of
+//! them or they are not that busy. When a channel is moving a lot of
+//! messages between two cores, though, each side's updates keep
+//! stealing the cache line out from under the other.
+//!
+//! Channels from [`spsc_padded`](crate::spsc_padded) instead give each
+//! side's position a cache line of its own, with the wakeup machinery
+//! on a third and the slots starting on a fourth. As in Erik Rigtorp's
+//! queue, each side only ever stores to its own line, and keeps a copy
+//! of the other's position which it refreshes only when that copy says
+//! it cannot make progress. A side which goes idle says so on the third
+//! line, so the other only reads its position when it might need
+//! waking.
+//!
+//! Messages may additionally be wrapped in [`CachePadded`] so that
+//! neighbouring slots do not share a cache line either:
+//!
+//! ```
+//! use async_spsc::{spsc_padded, CachePadded};
+//!
+//! let (mut sender, mut receiver) = spsc_padded::<CachePadded<u64>>(16);
+//! sender.send(CachePadded::new(42)).now().unwrap();
+//! let value = receiver.receive().now().unwrap().unwrap();
+//! assert_eq!(42, *value);
+//! ```
+#[cfg(feature="alloc")]
+use crate::{Atomics, DefaultNotify};
+use core::ops::{Deref, DerefMut};
+#[cfg(feature="alloc")]
+use crate::Side;
+#[cfg(feature="alloc")]
+use crate::AtomicWord;
+#[cfg(feature="alloc")]
+use crate::atomic::AtomicU8;
+
+/// Aligns (and so pads) a value to the size of a cache line, or of the
+/// pair of lines the prefetcher fetches together on targets which do
+/// that.
+#[cfg_attr(any(target_arch="x86_64", target_arch="aarch64", target_arch="powerpc64"), repr(align(128)))]
+#[cfg_attr(not(any(target_arch="x86_64", target_arch="aarch64", target_arch="powerpc64")), repr(align(64)))]
+#[derive(Clone,Copy,Debug,Default,Eq,Hash,PartialEq)]
+pub struct CachePadded<T>(T);
+
+impl<T> CachePadded<T> {
+    pub const fn new(value: T) -> Self { CachePadded(value) }
+
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<T> From<T> for CachePadded<T> {
+    fn from(value: T) -> Self { CachePadded(value) }
+}
+
+impl<T> Deref for CachePadded<T> {
+    type Target = T;
+    fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> DerefMut for CachePadded<T> {
+    fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+/// The header of a padded channel. The positions are kept as they
+/// would be in their half of the state. The `state` of the atomics
+/// only holds the close flags, so that exactly one side cleans up.
+#[cfg(feature="alloc")]
+#[derive(Debug,Default)]
+pub(crate) struct PaddedAtomics<N = DefaultNotify, A = AtomicWord> {
+    pub(crate) front:  CachePadded<AtomicWord>,
+    pub(crate) back:   CachePadded<AtomicWord>,
+    pub(crate) shared: CachePadded<Shared<N, A>>,
+}
+
+/// The parts of a padded channel which rarely change.
+#[cfg(feature="alloc")]
+#[derive(Debug,Default)]
+pub(crate) struct Shared<N, A> {
+    pub(crate) atomics: Atomics<N, A>,
+    /// Which sides have gone idle since they were last notified.
+    pub(crate) idle:    AtomicU8,
+}
+
+#[cfg(feature="alloc")]
+impl<N, A> PaddedAtomics<N, A> {
+    /// The bit in `idle` for `side`.
+    #[inline(always)]
+    pub(crate) fn idle_bit(side: Side) -> u8 {
+        match side {
+            Side::Sender => 1,
+            Side::Receiver => 2,
+        }
+    }
+}
@@ -35,88 +35,594 @@
 #[cfg(feature="alloc")]
 extern crate alloc;
 
+#[cfg(feature="std")]
+extern crate std;
+
+// Our atomics come from core, unless the target lacks the
+// read-modify-write operations we need and portable-atomic is to
+// provide them instead (with a critical section, if asked).
+mod atomic {
+    #[cfg(all(not(feature="portable-atomic"), not(target_has_atomic="ptr")))]
+    compile_error!("this target has no atomic compare-and-swap, enable the `portable-atomic` feature \
+                    (or `critical-section`, if it has a single core and no atomics at all)");
+    #[cfg(not(feature="portable-atomic"))]
+    pub(crate) use core::sync::atomic::*;
+    #[cfg(feature="portable-atomic")]
+    pub(crate) use portable_atomic::*;
+}
+
 use core::marker::PhantomData;
-use core::mem::MaybeUninit;
+use core::mem::{MaybeUninit, needs_drop, size_of};
 use core::ptr::{NonNull, drop_in_place};
-use core::sync::atomic::{AtomicUsize, Ordering};
-#[cfg(feature="async")]
-use core::{future::Future, pin::Pin, task::{Context, Poll}};
-
+use atomic::Ordering;
 #[cfg(feature="async")]
-use atomic_waker::AtomicWaker;
+use core::{future::Future, pin::Pin, task::{Context, Poll, Waker}};
 
 #[cfg(feature="alloc")]
 use pages::*;
 
 mod state;
 use state::*;
+pub mod notify;
+pub use notify::*;
+pub mod wait;
+pub use wait::*;
+pub mod capacity;
+pub use capacity::*;
+pub mod layout;
+pub use layout::CachePadded;
+pub mod lossy;
+pub mod unbounded;
+pub mod rendezvous;
+#[cfg(feature="alloc")]
+pub mod latest;
+#[cfg(feature="alloc")]
+pub use latest::*;
 pub mod sender;
 pub use sender::*;
 pub mod receiver;
 pub use receiver::*;
+#[cfg(feature="shm")]
+pub mod shm;
+#[cfg(feature="file")]
+pub mod file;
+#[cfg(feature="fd")]
+pub mod fd;
+#[cfg(feature="fd")]
+pub use fd::spsc_fd;
 
 // Sender/Receiver operation-local flags
+#[cfg(feature="async")]
 const WAITING: u8 = 1;
 
 #[derive(Debug)]
-enum Holder<'a, 'b, T> {
-    /// A pointer we do not own and will not attempt to free.
-    BorrowedPtr(NonNull<Spsc<'b, T>>, PhantomData<&'a ()>),
+enum Holder<'a, 'b, T, N, A = AtomicWord> {
+    /// A pointer we do not own and will not attempt to free. See
+    /// [`Spsc`].
+    BorrowedPtr(NonNull<Spsc<'b, T, N, A>>, PhantomData<&'a ()>),
     /// A pointer to a page we manage. This is an owned object we are
     /// abusing, so we need to suppress its destructor and manually
     /// drop it only when both sides are done.
     #[cfg(feature="alloc")]
-    Page(PageRef<Atomics, T>),
+    Page(PageRef<Atomics<N, A>, T>),
+    /// As `Page`, but each side's position has a cache line of its
+    /// own. See [`layout`].
+    #[cfg(feature="alloc")]
+    Padded(PageRef<layout::PaddedAtomics<N, A>, T>),
+    /// As `Page`, but the Sender may drop the oldest message to make
+    /// room. See [`lossy`].
+    #[cfg(feature="alloc")]
+    Lossy(PageRef<lossy::LossyAtomics<N, A>, T>),
+    /// As `Page`, but one of a chain the Sender links new segments
+    /// onto as it fills them. See [`unbounded`].
+    #[cfg(feature="alloc")]
+    Segment(PageRef<unbounded::Segment<T, N, A>, T>),
+    /// As `Page`, but each send waits for the Receiver to take the
+    /// message. See [`rendezvous`].
+    #[cfg(feature="alloc")]
+    Rendezvous(PageRef<Atomics<N, A>, T>),
+    /// Our own mapping of a region shared with another process. The
+    /// peer has a mapping of its own, so we unmap ours when we're
+    /// done whether or not we are the last referent.
+    #[cfg(feature="shm")]
+    Shm(shm::Mapping<T>),
     // // A pointer produced from [`Box::leak`] that's potentially
     // // shared with other holders.
     // #[cfg(feature="alloc")]
     // SharedBoxPtr(NonNull<Spsc<'b, T>>),
 }
 
-impl<'a, 'b, T> Clone for Holder<'a, 'b, T> {
-    fn clone(&self) -> Self {
+impl<'a, 'b, T, N, A> Clone for Holder<'a, 'b, T, N, A> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<'a, 'b, T, N, A> Copy for Holder<'a, 'b, T, N, A> {}
+
+impl<'a, 'b, T, N: Notify, A: AtomicState> Holder<'a, 'b, T, N, A> {
+
+    /// The process-local atomics. Not available for shm channels, whose
+    /// header lives in the shared region, or for segments, which share
+    /// the channel's notifier.
+    #[inline(always)]
+    fn atomics(&self) -> &Atomics<N, A> {
         match self {
-            Holder::BorrowedPtr(r, p) => Holder::BorrowedPtr(*r, *p),
+            Holder::BorrowedPtr(r, _) => &unsafe { r.as_ref() }.atomics,
+            #[cfg(feature="alloc")]
+            Holder::Page(p) | Holder::Rendezvous(p) => unsafe { p.header() },
+            #[cfg(feature="alloc")]
+            Holder::Padded(p) => unsafe { &p.header().shared.atomics },
             #[cfg(feature="alloc")]
-            Holder::Page(r) => Holder::Page(*r),
-            // #[cfg(feature="alloc")]
-            // Holder::SharedBoxPtr(r) => Holder::SharedBoxPtr(*r),
+            Holder::Lossy(p) => unsafe { &p.header().atomics },
+            #[cfg(feature="alloc")]
+            Holder::Segment(_) => unreachable!("segments have no notifier of their own"),
+            #[cfg(feature="shm")]
+            Holder::Shm(_) => unreachable!("shm channels have no local atomics"),
         }
     }
-}
 
-impl<'a, 'b, T> Copy for Holder<'a, 'b, T> {}
+    /// The local state word. See [`Holder::atomics`].
+    #[inline(always)]
+    fn state(&self) -> &A {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self { return unsafe { &p.header().atomics.state }; }
+        &self.atomics().state
+    }
 
-impl<'a, 'b, T> Holder<'a, 'b, T> {
+    /// The local watermarks. See [`Holder::atomics`].
+    #[inline(always)]
+    fn marks(&self) -> &A {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self { return unsafe { &p.header().atomics.marks }; }
+        &self.atomics().marks
+    }
 
+    /// The channel's notifier. See [`Holder::atomics`].
     #[inline(always)]
-    fn atomics(&self) -> *const Atomics {
+    fn notify(&self) -> &N {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self { return unsafe { p.header().notify() }; }
+        &self.atomics().notify
+    }
+
+    /// Loads the current state.
+    #[inline(always)]
+    fn load(&self) -> State {
         match self {
-            Holder::BorrowedPtr(r, _) => &unsafe { r.as_ref() }.atomics,
-            Holder::Page(p) => unsafe { p.header() }
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => State(m.state().load(Ordering::Acquire)),
+            #[cfg(feature="alloc")]
+            Holder::Padded(p) => {
+                let p = unsafe { p.header() };
+                State(p.front.load(Ordering::Acquire) | (p.back.load(Ordering::Acquire) << BITS))
+            }
+            // The reading flag is no concern of anyone else's.
+            #[cfg(feature="alloc")]
+            Holder::Lossy(_) | Holder::Rendezvous(_) =>
+                State(self.state().load_state(Ordering::Acquire).0 & !lossy::READING),
+            _ => self.state().load_state(Ordering::Acquire),
+        }
+    }
+
+    /// Flips the bits of `mask` in `side`'s half of the state,
+    /// returning the state from before.
+    #[inline(always)]
+    #[cfg_attr(not(feature="alloc"), allow(unused_variables))]
+    fn update(&self, side: Side, mask: Word) -> State {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => match side {
+                Side::Sender => m.update_sender(mask),
+                Side::Receiver => m.update_receiver(mask),
+            },
+            #[cfg(feature="alloc")]
+            Holder::Padded(p) => {
+                let p = unsafe { p.header() };
+                State(match side {
+                    Side::Sender => p.front.fetch_xor(mask, Ordering::AcqRel) | (p.back.load(Ordering::Acquire) << BITS),
+                    Side::Receiver => (p.back.fetch_xor(mask >> BITS, Ordering::AcqRel) << BITS) | p.front.load(Ordering::Acquire),
+                })
+            }
+            #[cfg(feature="alloc")]
+            Holder::Lossy(_) | Holder::Rendezvous(_) =>
+                State(self.state().xor_state(mask, Ordering::AcqRel).0 & !lossy::READING),
+            _ => self.state().xor_state(mask, Ordering::AcqRel),
+        }
+    }
+
+    /// Like `update`, for when `side` moves its own position on. `known`
+    /// is its copy of the state. Only `side` stores to its position on
+    /// a padded channel, so there we store it without reading it back,
+    /// and return `side`'s view of the peer rather than reading the
+    /// peer's line, unless the close flags (which are cheap to come by)
+    /// say the peer has closed. Its position is final then, and may be
+    /// ahead of our view of it.
+    #[inline(always)]
+    #[cfg_attr(not(feature="alloc"), allow(unused_variables))]
+    fn advance(&self, side: Side, mask: Word, known: State) -> State {
+        #[cfg(feature="alloc")]
+        if let Holder::Padded(p) = self {
+            let p = unsafe { p.header() };
+            // Pairs with `settle`, which the peer calls after flipping
+            // the close flag on its own line.
+            let closed = p.shared.atomics.state.load_state(Ordering::Acquire).is_closed();
+            return State(match side {
+                Side::Sender => {
+                    let front = p.front.load(Ordering::Relaxed);
+                    p.front.store(front ^ mask, Ordering::Release);
+                    let back = if closed { p.back.load(Ordering::Acquire) << BITS } else { known.0 & BACK };
+                    front | back
+                }
+                Side::Receiver => {
+                    let back = p.back.load(Ordering::Relaxed);
+                    p.back.store(back ^ (mask >> BITS), Ordering::Release);
+                    let front = if closed { p.front.load(Ordering::Acquire) } else { known.0 & FRONT };
+                    (back << BITS) | front
+                }
+            });
         }
+        self.update(side, mask)
     }
 
     #[inline(always)]
-    fn data(&mut self) -> *mut MaybeUninit<T> {
+    fn data(&self) -> *mut MaybeUninit<T> {
         match self {
             Holder::BorrowedPtr(r, _) => unsafe { r.as_ref() }.data(),
-            Holder::Page(p) => unsafe { p.data() },
+            #[cfg(feature="alloc")]
+            Holder::Page(p) | Holder::Rendezvous(p) => unsafe { p.data() },
+            #[cfg(feature="alloc")]
+            Holder::Padded(p) => unsafe { p.data() },
+            #[cfg(feature="alloc")]
+            Holder::Lossy(p) => unsafe { p.data() },
+            #[cfg(feature="alloc")]
+            Holder::Segment(p) => unsafe { p.data() },
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.data(),
         }
     }
 
+    /// The size of ring the positions in the state of a channel of `cap`
+    /// messages go round (wrapping at twice it). Zero-sized messages need
+    /// no slots, so a heap channel of them is only a count: its positions
+    /// wrap at the most the state can hold, and the capacity only bounds
+    /// how far apart they may get.
+    #[inline(always)]
+    fn ring(&self, cap: Half) -> Half {
+        #[cfg(feature="alloc")]
+        if size_of::<T>() == 0 && matches!(self, Holder::Page(_)) { return A::MAX_CAPACITY + 1; }
+        cap
+    }
+
+    /// The slot position `pos` in a ring of `cap` slots refers to.
+    /// Zero-sized messages have nowhere to go, so we need not work
+    /// out where.
+    #[inline(always)]
+    fn slot(&self, pos: HalfState, cap: Half) -> *mut MaybeUninit<T> {
+        if size_of::<T>() == 0 { return NonNull::dangling().as_ptr(); }
+        unsafe { self.data().add(pos.index(cap)) }
+    }
+
+    /// Loads the state on behalf of the Sender.
+    #[inline(always)]
+    fn refresh_sender(&self, cap: Half) -> State {
+        let (min, limit, ring) = (self.watermark(Side::Sender), self.limit(cap), self.ring(cap));
+        self.refresh(Side::Sender, |s| s.len(ring) >= limit, |s| limit.saturating_sub(s.len(ring)) >= min)
+    }
+
+    /// Loads the state on behalf of the Receiver.
+    #[inline(always)]
+    fn refresh_receiver(&self, cap: Half) -> State {
+        let (min, ring) = (self.watermark(Side::Receiver), self.ring(cap));
+        self.refresh(Side::Receiver, |s| s.is_empty(), |s| s.len(ring) >= min)
+    }
+
+    /// Loads the state on behalf of `side`, which has nothing to do in
+    /// `idle` states and would be notified in `ready` ones.
+    #[inline(always)]
+    fn refresh(&self, side: Side, idle: impl Fn(State) -> bool, ready: impl Fn(State) -> bool) -> State {
+        let state = self.load();
+        #[cfg(feature="shm")]
+        if let Holder::Shm(_) = self { return state; }
+        if state.is_closed() || !idle(state) { return state; }
+        self.idle(side);
+        let notify = self.notify();
+        if !notify.idle(side) { return state; }
+        // The peer may have made progress before we forgot.
+        let state = self.load();
+        if state.is_closed() || ready(state) { notify.notify(side, true); }
+        state
+    }
+
+    /// Marks `side` as idle on a padded channel, so that its peer will
+    /// look at its position to see whether to notify it. It must look
+    /// at the state again afterwards.
+    #[inline(always)]
+    #[cfg_attr(not(feature="alloc"), allow(unused_variables))]
+    fn idle(&self, side: Side) {
+        #[cfg(feature="alloc")]
+        if let Holder::Padded(p) = self {
+            let (idle, bit) = (&unsafe { p.header() }.shared.idle, layout::PaddedAtomics::<N>::idle_bit(side));
+            // Spare the line if we were already.
+            if idle.load(Ordering::Relaxed) & bit == 0 { idle.fetch_or(bit, Ordering::Relaxed); }
+            // Pairs with the fence in `wakeable`: either we see what
+            // they published, or they see we're idle.
+            atomic::fence(Ordering::SeqCst);
+        }
+    }
+
+    /// The state to decide whether to notify `side` by, given the
+    /// `next` state its peer just updated the atomic to. On a padded
+    /// channel, the peer only has its copy of `side`'s position, so it
+    /// looks at the real one if `side` has gone idle since it was last
+    /// notified, and otherwise returns None as there is no need.
+    #[inline(always)]
+    #[cfg_attr(not(feature="alloc"), allow(unused_variables))]
+    fn wakeable(&self, side: Side, next: State) -> Option<State> {
+        #[cfg(feature="alloc")]
+        if let Holder::Padded(p) = self {
+            atomic::fence(Ordering::SeqCst);
+            let bit = layout::PaddedAtomics::<N>::idle_bit(side);
+            let idle = unsafe { p.header() }.shared.idle.load(Ordering::Relaxed) & bit != 0;
+            return if idle || next.is_closed() { Some(self.load()) } else { None };
+        }
+        Some(next)
+    }
+
+    /// Whether `side` had gone idle, taking it that we are about to
+    /// notify it. `was_idle` is what the state said.
+    #[inline(always)]
+    #[cfg_attr(not(feature="alloc"), allow(unused_variables))]
+    fn notifying(&self, side: Side, was_idle: bool) -> bool {
+        #[cfg(feature="alloc")]
+        if let Holder::Padded(p) = self {
+            let bit = layout::PaddedAtomics::<N>::idle_bit(side);
+            return unsafe { p.header() }.shared.idle.fetch_and(!bit, Ordering::Relaxed) & bit != 0;
+        }
+        was_idle
+    }
+
+    /// The watermark `side` is woken at: the Receiver's minimum batch
+    /// or the Sender's minimum space.
+    #[inline(always)]
+    fn watermark(&self, side: Side) -> Half {
+        #[cfg(feature="shm")]
+        if let Holder::Shm(_) = self { return 1; }
+        let marks = self.marks().load_state(Ordering::Relaxed);
+        // They are stored less one, so the default is 1.
+        match side {
+            Side::Sender => marks.front().0 + 1,
+            Side::Receiver => marks.back().0 + 1,
+        }
+    }
+
+    #[inline(always)]
+    fn set_watermark(&self, side: Side, min: Half) {
+        #[cfg(feature="shm")]
+        if let Holder::Shm(_) = self { return; }
+        self.marks().update_state(|m| match side {
+            Side::Sender => m.with_front(HalfState(min - 1)),
+            Side::Receiver => m.with_back(HalfState(min - 1)),
+        });
+    }
+
+    /// Lets the Sender know there is (probably) space, if there is
+    /// enough. `prev` and `next` are the state before and after we
+    /// updated the atomic.
+    #[inline(always)]
+    fn wake_sender(&self, prev: State, next: State, cap: Half) {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.wake_sender(prev, cap),
+            // The Sender waits for each message to be taken.
+            #[cfg(feature="alloc")]
+            Holder::Rendezvous(_) => {
+                if next.is_closed() || next.is_empty() { self.notify().notify(Side::Sender, true); }
+            }
+            _ => {
+                let next = match self.wakeable(Side::Sender, next) {
+                    Some(next) => next,
+                    None => return,
+                };
+                let min = self.watermark(Side::Sender);
+                let (limit, ring) = (self.limit(cap), self.ring(cap));
+                let space = |s: State| limit.saturating_sub(s.len(ring));
+                if next.is_closed() || space(next) >= min {
+                    let was_idle = self.notifying(Side::Sender, space(prev) < min);
+                    self.notify().notify(Side::Sender, was_idle);
+                }
+            }
+        }
+    }
+
+    /// Lets the Receiver know there is (probably) a message, if there
+    /// are enough. `prev` and `next` are the state before and after we
+    /// updated the atomic. If `flush`, any message is enough.
+    #[inline(always)]
+    fn wake_receiver(&self, prev: State, next: State, cap: Half, flush: bool) {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.wake_receiver(prev),
+            _ => {
+                let next = match self.wakeable(Side::Receiver, next) {
+                    Some(next) => next,
+                    None => return,
+                };
+                let (min, ring) = (self.watermark(Side::Receiver), self.ring(cap));
+                let len = next.len(ring);
+                if next.is_closed() || len >= min || (flush && len > 0) {
+                    let was_idle = self.notifying(Side::Receiver, prev.len(ring) < min);
+                    self.notify().notify(Side::Receiver, was_idle);
+                }
+            }
+        }
+    }
+
+    /// Registers `waker` for `side`, unless it is still registered.
+    /// `last` is that side's copy of the waker it last registered.
+    ///
+    /// Note: the caller must check the state again afterwards.
+    #[cfg(feature="async")]
+    #[inline(always)]
+    fn register(&self, side: Side, waker: &Waker, last: &mut Option<Waker>) {
+        #[cfg(feature="shm")]
+        if let Holder::Shm(m) = self { return m.register(waker); }
+        self.idle(side);
+        let notify = self.notify();
+        let same = last.as_ref().is_some_and(|last| last.will_wake(waker));
+        if same && notify.registered(side) { return; }
+        notify.register(side, waker);
+        if !same { *last = Some(waker.clone()); }
+    }
+
+    #[cfg(feature="async")]
+    #[inline(always)]
+    fn unregister(&self, side: Side) {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.unregister(),
+            _ => self.notify().unregister(side),
+        }
+    }
+
+    /// The file descriptor for `side`, if the notifier has one.
+    #[cfg(feature="fd")]
+    #[inline(always)]
+    fn fd(&self, side: Side) -> std::os::unix::io::RawFd {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(_) => -1,
+            _ => self.notify().fd(side),
+        }
+    }
+
+    /// Blocks the Sender until the state is no longer `seen` (or
+    /// spins once, if we have no way of blocking).
+    #[inline(always)]
+    fn wait_sender(&self, seen: State) {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.wait_sender(seen),
+            _ => {
+                self.idle(Side::Sender);
+                self.notify().wait(Side::Sender, &|| self.load() == seen)
+            }
+        }
+    }
+
+    /// Blocks the Receiver until the state is no longer `seen` (or
+    /// spins once, if we have no way of blocking).
+    #[inline(always)]
+    fn wait_receiver(&self, seen: State) {
+        match self {
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.wait_receiver(seen),
+            _ => {
+                self.idle(Side::Receiver);
+                self.notify().wait(Side::Receiver, &|| self.load() == seen)
+            }
+        }
+    }
+
+    /// The word in which the sides settle who cleans up, for channels
+    /// which may still be touched after the state says both closed.
+    #[inline(always)]
+    fn closes(&self) -> Option<&A> {
+        match self {
+            // The close flags are spread over two words here, so we
+            // may both see the other closed.
+            #[cfg(feature="alloc")]
+            Holder::Padded(p) => Some(unsafe { &p.header().shared.atomics.state }),
+            // The Receiver moves on as soon as it sees the Sender has
+            // sealed a segment, which may be before it has woken it.
+            #[cfg(feature="alloc")]
+            Holder::Segment(p) => Some(unsafe { &p.header().closes }),
+            _ => None,
+        }
+    }
+
+    /// Closes `side`, flipping `mask` (which includes its close flag)
+    /// in its half of the state. Wakes the peer if it is still open or
+    /// cleans up if not. `known` is `side`'s local copy of the state.
+    /// Returns the state from before.
+    unsafe fn close(self, side: Side, mask: Word, cap: Half, known: State) -> State {
+        // If we already know they've closed, we're the last.
+        if known.is_closed() && self.closes().is_none() {
+            self.cleanup(cap, known);
+            return known;
+        }
+        let prev = self.update(side, mask);
+        let next = State(prev.0 ^ mask);
+        if self.closes().is_some() {
+            self.settle(side, prev, mask, cap);
+        } else if prev.is_closed() {
+            self.cleanup(cap, next);
+        } else {
+            self.wake_peer(side, prev, next, cap);
+            self.release();
+        }
+        prev
+    }
+
+    /// Having closed `side` by flipping `mask` in the `prev` state,
+    /// lets the peer know and then settles who cleans up (see
+    /// `closes`). We must not touch the channel afterwards, as that
+    /// may free it.
+    unsafe fn settle(self, side: Side, prev: State, mask: Word, cap: Half) {
+        if !prev.is_closed() { self.wake_peer(side, prev, State(prev.0 ^ mask), cap); }
+        if let Some(closes) = self.closes() {
+            if closes.xor_state(mask & ANY_CLOSE, Ordering::AcqRel).is_closed() { self.cleanup(cap, self.load()); }
+        }
+    }
+
+    /// Lets the peer of `side` know that it has closed.
+    #[inline(always)]
+    fn wake_peer(&self, side: Side, prev: State, next: State, cap: Half) {
+        match side {
+            Side::Sender => self.wake_receiver(prev, next, cap, true),
+            Side::Receiver => self.wake_sender(prev, next, cap),
+        }
+    }
+
+    /// Called when we are done with the spsc but were not the last
+    /// referent to it.
+    #[inline(always)]
+    fn release(self) {
+        #[cfg(feature="shm")]
+        if let Holder::Shm(m) = self { unsafe { m.unmap() } }
+    }
+
     // Safe only if we are the last referent to the spsc.
     unsafe fn cleanup(self, capacity: Half, state: State) {
         // whatever we are, we are going to drop the inflight items
         // and the wakers if there are any.
+        let capacity = self.ring(capacity);
         match self {
             Holder::BorrowedPtr(ptr, _) => 
                 ptr.as_ref().cleanup(capacity, state),
             #[cfg(feature="alloc")]
-            Holder::Page(c) => {
+            Holder::Page(c) | Holder::Rendezvous(c) => {
                 drop_in_flight(c.data(), capacity, state);
                 PageRef::drop(c);
             }
+            #[cfg(feature="alloc")]
+            Holder::Padded(c) => {
+                drop_in_flight(c.data(), capacity, state);
+                PageRef::drop(c);
+            }
+            #[cfg(feature="alloc")]
+            Holder::Lossy(c) => {
+                drop_in_flight(c.data(), capacity, state);
+                PageRef::drop(c);
+            }
+            #[cfg(feature="alloc")]
+            Holder::Segment(c) => {
+                drop_in_flight(c.data(), capacity, state);
+                c.header().free_notify();
+                PageRef::drop(c);
+            }
+            // Shm channels only carry `Copy` types, so there is
+            // nothing to drop.
+            #[cfg(feature="shm")]
+            Holder::Shm(m) => m.unmap(),
         }
     }
 
@@ -128,93 +634,332 @@ impl<'a, 'b, T> Holder<'a, 'b, T> {
 }
 
 fn drop_in_flight<T>(items: *mut MaybeUninit<T>, capacity: Half, state: State) {
+    if !needs_drop::<T>() { return; }
+    if size_of::<T>() == 0 {
+        // They all live in the same place, so we need only count.
+        for _ in 0..state.len(capacity) { unsafe { drop_in_place(NonNull::<T>::dangling().as_ptr()); } }
+        return;
+    }
     // TODO: probably not optimal
     let front = state.front().position();
-    let mut back = state.back();
+    // Strip the closed flag so we can advance it.
+    let mut back = HalfState(state.back().position());
     loop {
         let b = back.position();
         if front == b { break; }
-        let index = (b % capacity) as usize;
-        unsafe { drop_in_place(items.add(index));  }
+        unsafe { drop_in_place(items.add(back.index(capacity)).cast::<T>()); }
         back = back.advance(capacity, 1);
     }
 }
 
-/// Creates a new heap-backed [`Spsc`] that can store up to `capacity`
+/// Creates a new heap-backed channel that can store up to `capacity`
 /// in-flight messages at a time.
+///
+/// A capacity of 0 makes a rendezvous, where each send waits for the
+/// Receiver to take the message (see [`rendezvous`]).
+///
+/// Zero-sized messages (such as `()` for counting ticks) take up no
+/// room, so the channel is only a count in the state, which may go
+/// about twice as high as other channels' capacity. A capacity too
+/// large for it is capped at the most it can count rather than
+/// panicking.
+#[cfg(feature="alloc")]
 pub fn spsc<T>(capacity: Half) -> (Sender<'static, 'static, T>, Receiver<'static, 'static, T>) {
-    // First we must check we can handle this capacity.
+    spsc_with(capacity, DefaultNotify::default())
+}
+
+/// Like [`spsc`], but the sides will notify each other with `notify`
+/// instead of the [`DefaultNotify`].
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_with<T, N: Notify>(capacity: Half, notify: N)
+                               -> (Sender<'static, 'static, T, N>, Receiver<'static, 'static, T, N>) {
+    if capacity == 0 {
+        let ring = rendezvous::RENDEZVOUS_RING;
+        let atomics = Atomics { state: Default::default(), marks: Default::default(), notify };
+        let holder = Holder::Rendezvous(PageRef::new(atomics, pages(ring)));
+        return (Sender::new(holder, State(0), ring), Receiver::new(holder, State(0), ring));
+    }
+    alloc_spsc(capacity, notify)
+}
+
+/// Like [`spsc`], but the capacity is `N`, fixed at compile time (see
+/// [`Const`]). The sides do not need to store it and the index
+/// arithmetic on each send and receive is worked out in advance.
+///
+/// Note: will fail to compile if `N` is 0 or too large (which is about
+/// twice as large for zero-sized messages, as with [`spsc`]).
+///
+/// ```compile_fail
+/// let (sender, receiver) = async_spsc::spsc_const::<i32, 0>();
+/// ```
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_const<T, const N: Half>()
+    -> (Sender<'static, 'static, T, DefaultNotify, Const<N>>,
+        Receiver<'static, 'static, T, DefaultNotify, Const<N>>) {
+    const {
+        let max = if size_of::<T>() == 0 { <AtomicWord as AtomicState>::MAX_COUNT } else { MAX_CAPACITY };
+        assert!(N > 0 && N <= max, "spsc_const capacity must be between 1 and MAX_CAPACITY");
+    };
+    alloc_spsc(Const::<N>, DefaultNotify::default())
+}
+
+/// Like [`spsc`], but the capacity must be a power of two (see
+/// [`Pow2`]), so each send and receive finds its slot with a mask
+/// instead of a division.
+///
+/// Note: will panic if `capacity` is not a power of two or is too
+/// large.
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_pow2<T>(capacity: Half)
+    -> (Sender<'static, 'static, T, DefaultNotify, Pow2>, Receiver<'static, 'static, T, DefaultNotify, Pow2>) {
+    spsc_pow2_with(capacity, DefaultNotify::default())
+}
+
+/// Like [`spsc_pow2`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`].
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_pow2_with<T, N: Notify>(capacity: Half, notify: N)
+    -> (Sender<'static, 'static, T, N, Pow2>, Receiver<'static, 'static, T, N, Pow2>) {
+    alloc_spsc(Pow2::new(capacity), notify)
+}
+
+/// Like [`spsc`], but the capacity is stored as whichever of `u8`,
+/// `u16` or `u32` it is given and the state packed into an atomic twice
+/// as wide (see [`capacity`]), for when there are a great many small
+/// channels. This only shrinks the header they share: the Sender and
+/// Receiver are the same size whatever the width.
+///
+/// Note: will panic if `capacity` is 0 or too large for its width (63
+/// for `u8` and 16383 for `u16`). As with [`spsc`], zero-sized messages
+/// may be counted about twice as high, and are capped there instead.
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_compact<T, C: Capacity>(capacity: C)
+    -> (Sender<'static, 'static, T, DefaultNotify, C>, Receiver<'static, 'static, T, DefaultNotify, C>) {
+    spsc_compact_with(capacity, DefaultNotify::default())
+}
+
+/// Like [`spsc_compact`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`].
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_compact_with<T, N: Notify, C: Capacity>(capacity: C, notify: N)
+    -> (Sender<'static, 'static, T, N, C>, Receiver<'static, 'static, T, N, C>) {
+    alloc_spsc(capacity, notify)
+}
+
+/// Like [`spsc`], but laid out to avoid false sharing between the
+/// sides (see [`layout`]), for busy channels between two cores.
+#[cfg(feature="alloc")]
+pub fn spsc_padded<T>(capacity: Half) -> (Sender<'static, 'static, T>, Receiver<'static, 'static, T>) {
+    spsc_padded_with(capacity, DefaultNotify::default())
+}
+
+/// Like [`spsc_padded`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`].
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_padded_with<T, N: Notify>(capacity: Half, notify: N)
+                                      -> (Sender<'static, 'static, T, N>, Receiver<'static, 'static, T, N>) {
     assert!(capacity > 0);
     assert!(capacity <= MAX_CAPACITY);
-    let page = PageRef::new(Atomics::default(), capacity);
+    let atomics = Atomics { state: AtomicWord::new(0), marks: AtomicWord::new(0), notify };
+    let header = layout::PaddedAtomics {
+        front:  Default::default(),
+        back:   Default::default(),
+        shared: CachePadded::new(layout::Shared { atomics, idle: Default::default() }),
+    };
+    let holder = Holder::Padded(PageRef::new(header, pages(capacity)));
+    (Sender::new(holder, State(0), capacity), Receiver::new(holder, State(0), capacity))
+}
+
+/// Like [`spsc`], but sending into a full channel drops the oldest
+/// message instead of failing (see [`lossy`]).
+///
+/// Note: will panic if `capacity` is 0 or too large (about half the
+/// usual maximum).
+#[cfg(feature="alloc")]
+pub fn spsc_lossy<T>(capacity: Half) -> (Sender<'static, 'static, T>, Receiver<'static, 'static, T>) {
+    spsc_lossy_with(capacity, DefaultNotify::default())
+}
+
+/// Like [`spsc_lossy`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`].
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_lossy_with<T, N: Notify>(capacity: Half, notify: N)
+                                     -> (Sender<'static, 'static, T, N>, Receiver<'static, 'static, T, N>) {
+    assert!(capacity > 0);
+    assert!(capacity <= lossy::MAX_LOSSY_CAPACITY);
+    let atomics = Atomics { state: AtomicWord::new(0), marks: AtomicWord::new(0), notify };
+    let header = lossy::LossyAtomics { atomics, dropped: Default::default() };
+    // The ring has a slot spare for the message the Receiver is reading.
+    let ring = capacity + 1;
+    let holder = Holder::Lossy(PageRef::new(header, pages(ring)));
+    (Sender::new(holder, State(0), ring), Receiver::new(holder, State(0), ring))
+}
+
+/// Creates a channel which only keeps the latest value sent (see
+/// [`latest`](mod@latest)).
+#[cfg(feature="alloc")]
+pub fn latest<T>() -> (LatestSender<T>, LatestReceiver<T>) {
+    latest_with(DefaultNotify::default())
+}
+
+/// Like [`latest()`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`].
+#[cfg(feature="alloc")]
+pub fn latest_with<T, N: Notify>(notify: N) -> (LatestSender<T, N>, LatestReceiver<T, N>) {
+    latest::alloc_latest(notify)
+}
+
+/// Creates a channel which never fills up (see [`unbounded`]). The
+/// Sender moves on to a new segment of `segment` slots whenever it
+/// fills one, and the Receiver frees each once it has drained it.
+///
+/// Note: will panic if `segment` is 0 or too large.
+#[cfg(feature="alloc")]
+pub fn unbounded_spsc<T>(segment: Half) -> (Sender<'static, 'static, T>, Receiver<'static, 'static, T>) {
+    unbounded_spsc_with(segment, DefaultNotify::default())
+}
+
+/// Like [`unbounded_spsc`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`]. Every segment shares it.
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn unbounded_spsc_with<T, N: Notify>(segment: Half, notify: N)
+                                         -> (Sender<'static, 'static, T, N>, Receiver<'static, 'static, T, N>) {
+    assert!(segment > 0);
+    assert!(segment <= MAX_CAPACITY);
+    let notify = unbounded::alloc_notify(notify);
+    let holder = Holder::Segment(unbounded::alloc_segment(segment, true, notify, State(0), 0));
+    (Sender::new(holder, State(0), segment), Receiver::new(holder, State(0), segment))
+}
+
+/// Creates a channel of `capacity` which the Sender can resize with
+/// [`Sender::grow`] and [`Sender::shrink`] (see [`unbounded`]).
+///
+/// Note: will panic if `capacity` is 0 or too large.
+#[cfg(feature="alloc")]
+pub fn spsc_resizable<T>(capacity: Half) -> (Sender<'static, 'static, T>, Receiver<'static, 'static, T>) {
+    spsc_resizable_with(capacity, DefaultNotify::default())
+}
+
+/// Like [`spsc_resizable`], but the sides will notify each other with
+/// `notify` instead of the [`DefaultNotify`]. It is kept across resizes.
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+pub fn spsc_resizable_with<T, N: Notify>(capacity: Half, notify: N)
+                                         -> (Sender<'static, 'static, T, N>, Receiver<'static, 'static, T, N>) {
+    assert!(capacity > 0);
+    assert!(capacity <= MAX_CAPACITY);
+    let notify = unbounded::alloc_notify(notify);
+    let holder = Holder::Segment(unbounded::alloc_segment(capacity, false, notify, State(0), 0));
+    (Sender::new(holder, State(0), capacity), Receiver::new(holder, State(0), capacity))
+}
+
+/// The capacity as pages wants it.
+#[cfg(feature="alloc")]
+#[allow(clippy::unnecessary_cast)] // Half is narrower on some targets.
+fn pages(capacity: Half) -> u32 { capacity as u32 }
+
+/// Allocates the atomics and the slots together in a single page.
+#[cfg(feature="alloc")]
+#[allow(clippy::type_complexity)]
+fn alloc_spsc<T, N: Notify, C: Capacity>(capacity: C, notify: N)
+    -> (Sender<'static, 'static, T, N, C>, Receiver<'static, 'static, T, N, C>) {
+    // First we must check we can handle this capacity.
+    assert!(capacity.get() > 0);
+    // Zero-sized messages are only counted, as high as we can.
+    let capacity = if size_of::<T>() == 0 { capacity.resize(capacity.get().min(C::Atomic::MAX_COUNT)) } else { capacity };
+    assert!(capacity.get() <= C::Atomic::MAX_CAPACITY || size_of::<T>() == 0);
+    let atomics = Atomics { state: Default::default(), marks: Default::default(), notify };
+    let page = PageRef::new(atomics, pages(capacity.get()));
     let holder = Holder::Page(page);
     (Sender::new(holder, State(0), capacity), Receiver::new(holder, State(0), capacity))
 }
 
+/// A channel in a buffer we borrow rather than allocate, for when there
+/// is no allocator or the buffer should live somewhere in particular.
+/// [`split`](Spsc::split) it into a Sender and Receiver, which borrow
+/// it in turn.
+///
+/// ```
+/// use async_spsc::Spsc;
+/// use core::mem::MaybeUninit;
+///
+/// let mut buffer = [MaybeUninit::<i32>::uninit(); 2];
+/// let mut spsc = Spsc::new(&mut buffer);
+/// let (mut sender, mut receiver) = spsc.split();
+/// assert!(sender.send(42).now().is_ok());
+/// assert_eq!(receiver.receive().now(), Ok(Some(42)));
+/// ```
 #[derive(Debug)]
-pub struct Spsc<'a, T> {
-    atomics:  Atomics,
+pub struct Spsc<'a, T, N = DefaultNotify, A = AtomicWord> {
+    atomics:  Atomics<N, A>,
     ptr:      NonNull<MaybeUninit<T>>,
     capacity: Half,
-    _phantom: PhantomData<&'a T>
+    _phantom: PhantomData<&'a mut [MaybeUninit<T>]>
 }
 
 impl<'a, T> Spsc<'a, T> {
+    /// Creates a channel that can store up to `buffer.len()` in-flight
+    /// messages at a time.
+    ///
+    /// Note: will panic if `buffer` is empty or too long to represent.
+    pub fn new(buffer: &'a mut [MaybeUninit<T>]) -> Self {
+        Spsc::with_notify(buffer, DefaultNotify::default())
+    }
+}
+
+impl<'a, T, N: Notify> Spsc<'a, T, N> {
+    /// Like [`Spsc::new`], but the sides will notify each other with
+    /// `notify` instead of the [`DefaultNotify`].
+    pub fn with_notify(buffer: &'a mut [MaybeUninit<T>], notify: N) -> Self {
+        assert!(!buffer.is_empty(), "the spsc buffer must have a non-zero length");
+        assert!(buffer.len() <= MAX_CAPACITY as usize, "the spsc buffer is too long to represent");
+        let capacity = buffer.len() as Half;
+        let atomics = Atomics { state: Default::default(), marks: Default::default(), notify };
+        let ptr = NonNull::from(buffer).cast();
+        Spsc { atomics, ptr, capacity, _phantom: PhantomData }
+    }
+
+    /// Splits the channel into a Sender and Receiver. Any messages
+    /// still in flight when both have dropped are dropped with them, so
+    /// we may be split again afresh.
+    pub fn split(&mut self) -> (Sender<'_, 'a, T, N>, Receiver<'_, 'a, T, N>) {
+        // If a previous pair was leaked, so are their messages.
+        self.atomics.state = Default::default();
+        self.atomics.marks = Default::default();
+        let holder = Holder::BorrowedPtr(NonNull::from(&*self), PhantomData);
+        (Sender::new(holder, State(0), self.capacity), Receiver::new(holder, State(0), self.capacity))
+    }
+}
+
+impl<'a, T, N: Notify, A> Spsc<'a, T, N, A> {
     fn cleanup(&self, capacity: Half, state: State) {
         // Safe because we have exclusive access
         drop_in_flight(self.data(), capacity, state);
         // Avoid a potential memory leak.
-        self.atomics.drop_wakers();
+        self.atomics.notify.unregister(Side::Sender);
+        self.atomics.notify.unregister(Side::Receiver);
     }
 
     fn data(&self) -> *mut MaybeUninit<T> { self.ptr.as_ptr() }
 }
 
-impl<'a, T> Spsc<'a, T> {
-    // /// ## Safety
-    // ///
-    // /// * ptr must point to a len-sized array of appropriately aligned
-    // ///   and padded T which should already be initialised.
-    // ///
-    // /// Note: will panic if length is 0 or greater than can be
-    // /// represented in two bits less than half a usize.
-    // pub unsafe fn from_nonnull_len(ptr: NonNull<MaybeUninit<T>>, len: Half) -> Self {
-    //     assert!(len > 0, "the spsc buffer must have a non-zero length");
-    //     assert!(len <= MAX_CAPACITY, "the spsc buffer must have a length representable in two bits less than half a usize")
-    //     Spsc();
-    //     Self::make(Slice::BorrowedPtrLen(ptr, len))
-    // }
-
-//     // /// ## Safety
-//     // ///
-//     // /// * len must not be zero
-//     // /// * ptr must point to a len-sized array of appropriately aligned
-//     // ///   and padded T which should already be initialised.
-//     // ///
-//     // /// Note: will panic if length is 0 or greater than can be
-//     // /// represented in two bits less than half a usize.
-//     // pub unsafe fn from_raw_parts(ptr: *mut MaybeUninit<T>, len: Half) -> Self {
-//     //     assert!(len > 0, "the spsc buffer must have a non-zero length");
-//     //     assert!(len <= MAX_CAPACITY, "the spsc buffer must have a length representable in two bits less than half a usize");
-//     //     Self::make(Slice::BorrowedPtrLen(NonNull::new_unchecked(ptr), len))
-//     // }
-
-}
-
 #[derive(Debug,Default)]
-pub struct Atomics {
-    state:    AtomicUsize,
-    #[cfg(feature="async")]
-    sender:   AtomicWaker,
-    #[cfg(feature="async")]
-    receiver: AtomicWaker,
-}
-
-impl Atomics {
-    fn drop_wakers(&self) {
-        let _s = self.sender.take();
-        let _r = self.receiver.take();
-    }
+pub struct Atomics<N = DefaultNotify, A = AtomicWord> {
+    state:  A,
+    // Watermarks (less one), packed like the state: the Sender's
+    // minimum space in the front and Receiver's minimum batch in the
+    // back.
+    marks:  A,
+    notify: N,
 }
 
 #[derive(Debug,Eq,Hash,PartialEq)]
@@ -230,3 +975,14 @@ pub enum SendErrorKind {
 
 #[derive(Debug,Eq,Hash,PartialEq)]
 pub struct Closed;
+
+/// Why [`Sender::grow`] or [`Sender::shrink`] failed.
+#[cfg(feature="alloc")]
+#[derive(Debug,Eq,Hash,PartialEq)]
+pub enum ResizeError {
+    Closed,
+    /// The channel was not made with [`spsc_resizable`].
+    NotResizable,
+    /// More messages are in flight than the new capacity allows.
+    Full,
+}
@@ -0,0 +1,131 @@
+//! Channels which drop the oldest message rather than fill up.
+//!
+//! For telemetry and the like, where the newest samples matter more
+//! than every sample arriving, [`spsc_lossy`] makes
+//! a channel whose Sender never finds it full: sending into a full
+//! ring evicts the oldest message the Receiver has yet to take. The
+//! Receiver can find out how many were lost with
+//! [`Receiver::dropped`].
+//!
+//! ```
+//! use async_spsc::spsc_lossy;
+//!
+//! let (mut sender, mut receiver) = spsc_lossy::<i32>(2);
+//! for i in 0..5 { sender.send(i).now().unwrap(); }
+//! assert_eq!(Ok(Some(3)), receiver.receive().now());
+//! assert_eq!(Ok(Some(4)), receiver.receive().now());
+//! assert_eq!(3, receiver.dropped());
+//! ```
+//!
+//! Both sides move the back of the state here, so they do so with a
+//! compare and swap. The Receiver also marks the message it is taking
+//! as being read, in a bit of its half the positions leave free, and
+//! the Sender does not evict while it is set. The ring has a slot
+//! spare, so the Sender can still send one more without touching the
+//! message being read. Should the ring fill up again before the
+//! Receiver is done, it is the new message which is dropped.
+use crate::*;
+#[cfg(feature="alloc")]
+use crate::atomic::AtomicUsize;
+
+/// Set in the back half while the Receiver is reading the message
+/// before it.
+pub(crate) const READING: Word = ((HIGH_BIT >> 1) as Word) << BITS;
+
+/// The largest capacity of a lossy channel, whose positions must leave
+/// room for the reading flag and the spare slot.
+#[cfg(feature="alloc")]
+pub(crate) const MAX_LOSSY_CAPACITY: Half = (1 << (BITS - 3)) - 1;
+
+/// The header of a lossy channel.
+#[cfg(feature="alloc")]
+#[derive(Debug,Default)]
+pub(crate) struct LossyAtomics<N = DefaultNotify, A = AtomicWord> {
+    pub(crate) atomics: Atomics<N, A>,
+    // How many messages have been dropped, wrapping.
+    pub(crate) dropped: AtomicUsize,
+}
+
+impl<'a, 'b, T, N: Notify, A: AtomicState> Holder<'a, 'b, T, N, A> {
+
+    /// Whether the Sender evicts messages rather than finding the ring
+    /// full.
+    #[inline(always)]
+    pub(crate) fn is_lossy(&self) -> bool {
+        #[cfg(feature="alloc")]
+        return matches!(self, Holder::Lossy(_));
+        #[cfg(not(feature="alloc"))]
+        false
+    }
+
+    /// Whether the Receiver claims messages before reading them, so that
+    /// the Sender can tell which it may take back.
+    #[inline(always)]
+    pub(crate) fn claims(&self) -> bool { self.is_lossy() || self.is_rendezvous() }
+
+    /// How many messages may be in flight in a ring of `cap` slots.
+    /// Lossy and rendezvous channels keep one spare for the Receiver to
+    /// read from.
+    #[inline(always)]
+    pub(crate) fn limit(&self, cap: Half) -> Half { cap - self.claims() as Half }
+
+    /// How many messages have been dropped so far.
+    pub(crate) fn dropped(&self) -> usize {
+        #[cfg(feature="alloc")]
+        if let Holder::Lossy(p) = self { return unsafe { p.header() }.dropped.load(Ordering::Relaxed); }
+        0
+    }
+
+    #[inline(always)]
+    pub(crate) fn count_dropped(&self) {
+        #[cfg(feature="alloc")]
+        if let Holder::Lossy(p) = self { unsafe { p.header() }.dropped.fetch_add(1, Ordering::Relaxed); }
+    }
+
+    /// Makes room to send into a ring of `cap` slots which may hold
+    /// `limit` messages, dropping the oldest if need be. Returns the
+    /// state once there is room (or a side has closed), or `None` if
+    /// the Receiver is still reading the oldest and we may not.
+    pub(crate) fn evict(&self, cap: Half, limit: Half) -> Option<State> {
+        let atomic = self.state();
+        let mut seen = atomic.load_state(Ordering::Acquire);
+        loop {
+            let state = State(seen.0 & !READING);
+            if state.is_closed() || state.len(cap) < limit { return Some(state); }
+            if seen.0 & READING != 0 { return None; }
+            let back = state.back();
+            let next = state.with_back(back.advance(cap, 1));
+            match atomic.swap_state(seen, next, Ordering::AcqRel) {
+                Ok(_) => {
+                    // The Receiver can no longer claim it, so it is ours.
+                    self.count_dropped();
+                    drop(unsafe { self.slot(back, cap).read().assume_init() });
+                    return Some(next);
+                }
+                Err(s) => seen = s,
+            }
+        }
+    }
+
+    /// Claims the oldest message for the Receiver, marking it as being
+    /// read until it is [`taken`](Self::taken). `seen` is our guess at
+    /// the state. Returns the state we claimed it from, or the state if
+    /// there turned out to be nothing to claim.
+    pub(crate) fn claim(&self, mut seen: State, cap: Half) -> Result<State, State> {
+        let atomic = self.state();
+        loop {
+            if seen.is_empty() { return Err(seen); }
+            let next = State(seen.with_back(seen.back().advance(cap, 1)).0 | READING);
+            match atomic.swap_state(seen, next, Ordering::AcqRel) {
+                Ok(_) => return Ok(seen),
+                Err(s) => seen = s,
+            }
+        }
+    }
+
+    /// Lets the Sender evict again once we have read the message we
+    /// claimed, returning the state afterwards.
+    pub(crate) fn taken(&self) -> State {
+        State(self.state().xor_state(READING, Ordering::AcqRel).0 ^ READING)
+    }
+}
@@ -0,0 +1,301 @@
+//! How one side of a channel lets the other know it can make progress.
+//!
+//! Every channel is built around a [`Notify`], chosen when it is
+//! created with [`spsc_with`](crate::spsc_with). The ring logic is the
+//! same whichever is used, so different parts of a program may use
+//! different wakeup mechanisms:
+//!
+//! * [`Wakers`] (the default) wakes tasks awaiting the futures, and
+//!   with `std` unparks threads blocked in `wait()` too.
+//! * [`Park`] unparks threads blocked in `wait()`.
+//! * [`EventFds`](crate::fd::EventFds) makes an eventfd readable, for
+//!   use with epoll or mio.
+//! * [`Callback`] runs a function of your choosing.
+//! * [`NoOp`] does nothing at all, for when you only ever use `now()`.
+//!
+//! Channels in shared memory always use futexes and are not affected.
+use core::task::Waker;
+#[cfg(feature="async")]
+use core::cell::UnsafeCell;
+#[cfg(feature="async")]
+use crate::atomic::{AtomicU8, Ordering, fence};
+
+/// One end of a channel.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub enum Side {
+    Sender,
+    Receiver,
+}
+
+/// A wakeup mechanism for a channel. Shared between both sides, so it
+/// must be safe to use from the threads either is used on.
+pub trait Notify: Send + Sync {
+    /// Tells `side` its peer has made enough progress for it to be
+    /// worth waking (see `set_min_batch` and `set_min_space`) or has
+    /// closed.
+    ///
+    /// `was_idle` is whether `side` had too little to do before the
+    /// change (the ring was empty or held fewer than the minimum batch,
+    /// for the Receiver, or had less than the minimum space, for the
+    /// Sender). If it is false, we have already notified `side` since
+    /// it last found nothing to do.
+    fn notify(&self, side: Side, was_idle: bool);
+
+    /// Called when `side` finds it has nothing to do. Return true if
+    /// this forgets an earlier notification, in which case we will
+    /// check again and renotify if the peer has since made progress.
+    fn idle(&self, _side: Side) -> bool { false }
+
+    /// Called when a future for `side` is about to return `Pending`.
+    /// We will check the state again afterwards, so it is fine to
+    /// skip notifications which come before this.
+    fn register(&self, _side: Side, _waker: &Waker) {}
+
+    /// Whether the waker last registered for `side` is still waiting
+    /// to be woken. If so, and the waker would wake the same task,
+    /// registering it again may be skipped.
+    fn registered(&self, _side: Side) -> bool { false }
+
+    /// Called when a future for `side` is dropped while waiting, and
+    /// when the channel is cleaned up.
+    fn unregister(&self, _side: Side) {}
+
+    /// A file descriptor which becomes readable when `side` may make
+    /// progress, or -1 if there is none.
+    #[cfg(feature="fd")]
+    fn fd(&self, _side: Side) -> std::os::unix::io::RawFd { -1 }
+
+    /// Blocks the current thread on behalf of `side` while `idle`
+    /// returns true. Implementations must prepare to be notified
+    /// before they call `idle`. Spurious returns are fine.
+    ///
+    /// The default has no way to be woken, so it just yields the
+    /// thread once (or, without std, spins once).
+    fn wait(&self, _side: Side, idle: &dyn Fn() -> bool) {
+        if idle() {
+            #[cfg(feature="std")]
+            std::thread::yield_now();
+            #[cfg(not(feature="std"))]
+            core::hint::spin_loop();
+        }
+    }
+}
+
+/// The default [`Notify`], waking the tasks awaiting either side.
+///
+/// Both sides may be waiting at once (e.g. for
+/// [`wait_space`](crate::Sender::wait_space) and
+/// [`wait_len`](crate::Receiver::wait_len)), so each has a waker slot
+/// of its own. They share one word of flags, in which each side has a
+/// waiting bit, so the peer only wakes it when it is actually waiting.
+///
+/// Each slot is guarded as in `AtomicWaker`: whoever sets its side's
+/// `REGISTERING` or `WAKING` bit while neither is set has it to
+/// themselves. Nobody ever waits for the other. A notifier which loses
+/// the race leaves `WAKING` set for the registrant to act on, and a
+/// registrant which loses it wakes its own waker so its task will look
+/// again.
+#[cfg(feature="async")]
+#[derive(Debug,Default)]
+pub struct Wakers {
+    sender:   UnsafeCell<Option<Waker>>,
+    receiver: UnsafeCell<Option<Waker>>,
+    flags:    AtomicU8,
+}
+
+// The Sender's flags. The Receiver's are shifted left by one.
+#[cfg(feature="async")]
+const WAITING: u8 = 1;
+#[cfg(feature="async")]
+const REGISTERING: u8 = 4;
+#[cfg(feature="async")]
+const WAKING: u8 = 16;
+
+#[cfg(feature="async")]
+impl Wakers {
+    #[inline(always)]
+    fn shift(side: Side) -> u32 {
+        match side {
+            Side::Sender => 0,
+            Side::Receiver => 1,
+        }
+    }
+
+    /// The slot for `side`, which only whoever has claimed it may use.
+    #[inline(always)]
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slot(&self, side: Side) -> &mut Option<Waker> {
+        match side {
+            Side::Sender => &mut *self.sender.get(),
+            Side::Receiver => &mut *self.receiver.get(),
+        }
+    }
+
+    /// Claims `side`'s slot for registering, returning the flags from
+    /// before, or None if a registrant or notifier has it.
+    #[inline(always)]
+    fn claim(&self, side: Side) -> Option<u8> {
+        let shift = Self::shift(side);
+        let mut flags = self.flags.load(Ordering::Relaxed);
+        loop {
+            if flags & ((REGISTERING | WAKING) << shift) != 0 { return None; }
+            match self.flags.compare_exchange_weak(flags, flags | (REGISTERING << shift), Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => return Some(flags),
+                Err(f) => flags = f,
+            }
+        }
+    }
+
+    /// Takes `side`'s waker, unless someone else has the slot, in
+    /// which case they will deal with it.
+    #[inline(always)]
+    fn take(&self, side: Side) -> Option<Waker> {
+        let shift = Self::shift(side);
+        let flags = self.claim(side)?;
+        // Safe because we have claimed the slot.
+        let waker = if flags & (WAITING << shift) != 0 { unsafe { self.slot(side) }.take() } else { None };
+        // Anyone who came to wake meanwhile was after the waker we
+        // just took, since it's the only one there was.
+        self.flags.fetch_and(!((WAITING | REGISTERING | WAKING) << shift), Ordering::Release);
+        waker
+    }
+}
+
+#[cfg(feature="async")]
+impl Notify for Wakers {
+    #[inline(always)]
+    fn notify(&self, side: Side, _was_idle: bool) {
+        let shift = Self::shift(side);
+        // Pairs with the fence in `register`: either they see our
+        // update to the state or we see their bit.
+        fence(Ordering::SeqCst);
+        if self.flags.load(Ordering::Relaxed) & (WAITING << shift) == 0 { return; }
+        let flags = self.flags.fetch_or(WAKING << shift, Ordering::AcqRel);
+        // If they're registering, they'll see WAKING and look again.
+        if flags & ((REGISTERING | WAKING) << shift) != 0 { return; }
+        // Safe because we have claimed the slot.
+        let waker = if flags & (WAITING << shift) != 0 { unsafe { self.slot(side) }.take() } else { None };
+        self.flags.fetch_and(!((WAKING | WAITING) << shift), Ordering::Release);
+        if let Some(waker) = waker { waker.wake(); }
+    }
+
+    fn register(&self, side: Side, waker: &Waker) {
+        let shift = Self::shift(side);
+        // Clone before we claim the slot, so the peer never waits on
+        // someone else's code.
+        let waker = waker.clone();
+        if self.claim(side).is_none() {
+            // The peer is in the middle of waking (or we are in the
+            // middle of unregistering), so whatever we were waiting for
+            // may have happened.
+            return waker.wake();
+        }
+        // Safe because we have claimed the slot.
+        let old = unsafe { self.slot(side) }.replace(waker);
+        let set = |f: u8| Some((f & !((REGISTERING | WAKING) << shift)) | (WAITING << shift));
+        let woken = self.flags.fetch_update(Ordering::AcqRel, Ordering::Relaxed, set).unwrap() & (WAKING << shift) != 0;
+        fence(Ordering::SeqCst);
+        drop(old);
+        // Someone came to wake while we held the slot.
+        if woken {
+            if let Some(waker) = self.take(side) { waker.wake(); }
+        }
+    }
+
+    fn registered(&self, side: Side) -> bool {
+        self.flags.load(Ordering::Acquire) & (WAITING << Self::shift(side)) != 0
+    }
+
+    fn unregister(&self, side: Side) {
+        if !self.registered(side) { return; }
+        self.take(side);
+    }
+
+    /// Registers a waker which unparks this thread, then parks it.
+    #[cfg(feature="std")]
+    fn wait(&self, side: Side, idle: &dyn Fn() -> bool) {
+        let waker = Waker::from(std::sync::Arc::new(Unpark(std::thread::current())));
+        self.register(side, &waker);
+        if idle() { std::thread::park(); }
+        self.unregister(side);
+    }
+}
+
+/// Wakes a thread blocked in [`Wakers::wait`].
+#[cfg(all(feature="async", feature="std"))]
+struct Unpark(std::thread::Thread);
+
+#[cfg(all(feature="async", feature="std"))]
+impl std::task::Wake for Unpark {
+    fn wake(self: std::sync::Arc<Self>) { self.0.unpark(); }
+    fn wake_by_ref(self: &std::sync::Arc<Self>) { self.0.unpark(); }
+}
+
+// Safe because each waker is only accessed by whoever claimed its slot.
+#[cfg(feature="async")]
+unsafe impl Send for Wakers {}
+#[cfg(feature="async")]
+unsafe impl Sync for Wakers {}
+
+/// Notifies by unparking the thread last blocked in `wait()` on each
+/// side. Futures will not be woken.
+#[cfg(feature="std")]
+#[derive(Debug,Default)]
+pub struct Park {
+    sender:   std::sync::Mutex<Option<std::thread::Thread>>,
+    receiver: std::sync::Mutex<Option<std::thread::Thread>>,
+}
+
+#[cfg(feature="std")]
+impl Park {
+    fn get(&self, side: Side) -> std::sync::MutexGuard<'_, Option<std::thread::Thread>> {
+        let thread = match side {
+            Side::Sender => &self.sender,
+            Side::Receiver => &self.receiver,
+        };
+        thread.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature="std")]
+impl Notify for Park {
+    fn notify(&self, side: Side, was_idle: bool) {
+        if was_idle {
+            if let Some(thread) = self.get(side).as_ref() { thread.unpark(); }
+        }
+    }
+
+    fn wait(&self, side: Side, idle: &dyn Fn() -> bool) {
+        *self.get(side) = Some(std::thread::current());
+        if idle() { std::thread::park(); }
+    }
+}
+
+/// Notifies by calling a function with the side which may now make
+/// progress. Only called when that side had too little to do before.
+///
+/// Note: this is called on whichever thread the peer is using, so it
+/// should be quick.
+#[derive(Debug)]
+pub struct Callback<F>(pub F);
+
+impl<F: Fn(Side) + Send + Sync> Notify for Callback<F> {
+    fn notify(&self, side: Side, was_idle: bool) {
+        if was_idle { (self.0)(side) }
+    }
+}
+
+/// Does nothing, for channels only ever used with `now()`.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct NoOp;
+
+impl Notify for NoOp {
+    fn notify(&self, _side: Side, _was_idle: bool) {}
+}
+
+/// The [`Notify`] used when none is chosen.
+#[cfg(feature="async")]
+pub type DefaultNotify = Wakers;
+/// The [`Notify`] used when none is chosen.
+#[cfg(not(feature="async"))]
+pub type DefaultNotify = NoOp;
@@ -4,86 +4,144 @@ use core::cell::Cell;
 // #[cfg(feature="stream")]
 // use futures_core::stream::Stream;
 
-pub struct Receiver<'a, 'b, T> {
-    spsc:  Option<Holder<'a, 'b, T>>,
+pub struct Receiver<'a, 'b, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    spsc:  Option<Holder<'a, 'b, T, N, C::Atomic>>,
     state: Cell<State>,
-    cap:   Half,
+    cap:   C,
+    wait:  WaitStrategy,
+    // Whether `poll_recv` has left a waker registered.
+    #[cfg(feature="async")]
+    polled: bool,
 }
 
-impl<'a, 'b, T> Receiver<'a, 'b, T> {
+impl<'a, 'b, T, N: Notify, C: Capacity> Receiver<'a, 'b, T, N, C> {
 
-    pub(super) fn new(spsc: Holder<'a, 'b, T>, state: State, cap: Half) -> Self {
-        Receiver { spsc: Some(spsc), state: Cell::new(state), cap }
+    pub(super) fn new(spsc: Holder<'a, 'b, T, N, C::Atomic>, state: State, cap: C) -> Self {
+        Receiver {
+            spsc: Some(spsc),
+            state: Cell::new(state),
+            cap,
+            wait: WaitStrategy::Park,
+            #[cfg(feature="async")]
+            polled: false,
+        }
     }
 
-    fn refresh_state(&mut self) -> State {
-        let atomics = self.spsc.as_mut().unwrap().atomics();
-        let state = State(atomics.state.load(Ordering::Acquire));
-        self.state.set(state);
-        state
-    }
+    /// Sets what we do when we find the channel empty. See [`WaitStrategy`].
+    pub fn set_wait_strategy(&mut self, wait: WaitStrategy) { self.wait = wait; }
 
-    fn update_state(&mut self, mask: Half) -> State {
-        let atomics = self.spsc.as_mut().unwrap().atomics();
-        let mask = (mask as usize) << BITS;
-        let state = State(atomics.state.fetch_xor(mask, Ordering::Acquire) ^ mask);
-        self.state.set(state);
-        state
+    /// When we are waiting for a message, only wake us once at least
+    /// `min` are queued (or the Sender closes), rather than as soon as
+    /// one is. The default is 1.
+    ///
+    /// Note: will panic if `min` is 0 or more than the capacity (1 for a
+    /// rendezvous). Has no effect on shm channels.
+    pub fn set_min_batch(&mut self, min: Half) {
+        assert!(min > 0 && min <= self.limit());
+        if let Some(spsc) = self.spsc.as_ref() { spsc.set_watermark(Side::Receiver, min); }
     }
 
+    /// How many messages a lossy channel (see [`spsc_lossy`]) has
+    /// dropped to make room for newer ones, wrapping on overflow.
+    /// Always 0 for others.
+    pub fn dropped(&self) -> usize { self.spsc.as_ref().map_or(0, |spsc| spsc.dropped()) }
+
     /// Returns a disposable object which can receive a single message
     /// either synchronously via [`Receiving::now`] or asynchronously
     /// via the [`core::future::Future`] instance.
-    pub fn receive<'c>(&'c mut self) -> Receiving<'a, 'b, 'c, T> {
-        Receiving { receiver: Some(self) }
+    pub fn receive<'c>(&'c mut self) -> Receiving<'a, 'b, 'c, T, N, C> {
+        Receiving {
+            receiver: Some(self),
+            #[cfg(feature="async")]
+            waker:    None,
+        }
     }
-}
 
+    /// As [`receive`](Self::receive), but the future owns us rather
+    /// than borrowing us, and hands us back with the result. It can
+    /// thus be stored or spawned apart from wherever we came from.
+    #[cfg(feature="async")]
+    pub fn into_recv(self) -> IntoReceiving<'a, 'b, T, N, C> {
+        IntoReceiving { receiver: Some(self), waker: None }
+    }
 
+    /// Returns a future which resolves to the number of queued messages
+    /// once there are at least `n`, without receiving anything. Fails
+    /// if the Sender closes with fewer queued.
+    ///
+    /// Note: will panic if `n` is 0 or more than the capacity (1 for a
+    /// rendezvous, where a send is waiting to be taken). If a minimum
+    /// batch larger than `n` has been set, we will not be woken until it
+    /// is reached (or the Sender closes).
+    #[cfg(feature="async")]
+    pub fn wait_len<'c>(&'c mut self, n: Half) -> WaitLen<'a, 'b, 'c, T, N, C> {
+        assert!(n > 0 && n <= self.limit());
+        WaitLen { receiver: self, n, waker: None }
+    }
 
-unsafe impl<'a, 'b, T: Send> Send for Receiver<'a, 'b, T> {}
-unsafe impl<'a, 'b, T: Send> Sync for Receiver<'a, 'b, T> {}
+    /// How many messages may be in flight in our ring. See
+    /// `Holder::limit`.
+    #[inline(always)]
+    fn limit(&self) -> Half {
+        self.spsc.as_ref().map_or(self.cap.get(), |spsc| spsc.limit(self.cap.get()))
+    }
 
-impl<'a, 'b, T> Drop for Receiver<'a, 'b, T> {
-    fn drop(&mut self) {
-        if let Some(spsc) = self.spsc.take() {
-            // If we already know they've closed, clean up.
-            let state = self.state.get();
-            if state.is_closed() {
-                unsafe { spsc.cleanup(self.cap, state); }
-                return;
-            }
-            // Mark ourselves closed
-            let atomics = spsc.atomics();
-            let state2 = State(atomics.state.fetch_xor(R_CLOSE, Ordering::AcqRel));
-            if state2.is_closed() {
-                // We were beaten to it. 
-                unsafe { spsc.cleanup(self.cap, state); }
-            } else {
-                // We should wake them
-                atomics.sender.wake();
+    /// Takes the oldest message, given a `state` in which there is one,
+    /// and hands its slot back to the Sender. Should the Sender have
+    /// evicted or withdrawn them all first, returns the state instead.
+    #[inline(always)]
+    fn take(&self, spsc: &Holder<'a, 'b, T, N, C::Atomic>, state: State) -> Result<T, State> {
+        let cap = self.cap.get();
+        let (value, prev, state) = if spsc.claims() {
+            // The Sender may evict or withdraw it until we have
+            // claimed it.
+            let prev = spsc.claim(state, cap).inspect_err(|state| self.state.set(*state))?;
+            let value = unsafe { spsc.slot(prev.back(), cap).read().assume_init() };
+            (value, prev, spsc.taken())
+        } else {
+            let back = state.back();
+            // This mouthful takes the value, leaving the slot uninitialised
+            let value = unsafe { spsc.slot(back, cap).read().assume_init() };
+            // Now inform the Sender they can have this slot back.
+            let b = back.advance(spsc.ring(cap), 1);
+            let mask = ((back.0 ^ b.0) as Word) << BITS;
+            let state = State(spsc.advance(Side::Receiver, mask, state).0 ^ mask);
+            (value, State(state.0 ^ mask), state)
+        };
+        self.state.set(state);
+        // Now we attempt to wake the Sender if they are not
+        // closed. There will probably be nothing here.
+        if !state.is_closed() { spsc.wake_sender(prev, state, cap); }
+        Ok(value)
+    }
+
+    /// Receives a message if there is one, without waiting, moving on
+    /// to the next segment of an unbounded channel as need be.
+    fn receive_now(&mut self) -> Result<Option<T>, Closed> {
+        loop {
+            match self.receive_segment() {
+                // The end of a segment, rather than of the channel.
+                Err(Closed) if self.next_segment() => (),
+                // The last of a sealed segment, so we move on now to give
+                // the Sender the room they took in the next.
+                Ok(Some(value)) => {
+                    self.next_segment();
+                    return Ok(Some(value));
+                }
+                ret => return ret,
             }
         }
     }
-}
 
-/// A single Receive operation that can be performed synchronously
-/// (with [`Receiving::now`]) or asynchronously (with the
-/// [`core::future::Future`] instance).
-pub struct Receiving<'a, 'b, 'c, T> {
-    receiver: Option<&'c mut Receiver<'a, 'b, T>>,
-}
-
-impl<'a, 'b, 'c, T> Receiving<'a, 'b, 'c, T> {
-    pub fn now(mut self) -> Result<Option<T>, Closed> {
-        // Take our receiver, since we can't be called again.
-        let receiver = self.receiver.take().unwrap();
-        if let Some(spsc) = receiver.spsc.as_mut() {
-            let cap = receiver.cap;
+    /// As [`receive_now`](Self::receive_now), but only from the
+    /// segment we are on.
+    fn receive_segment(&mut self) -> Result<Option<T>, Closed> {
+        if let Some(spsc) = self.spsc {
+            let cap = self.cap.get();
             // We are going to first check our local cached state. If
             // it tells us there is space, we don't need to
             // synchronise to receive!
-            let mut state = receiver.state.get();
+            let mut state = self.state.get();
             // The Receiver is slightly different logic to the Sender
             // since if there are still messages in flight, we can
             // receive them even if the Sender closed. Thus if we hit
@@ -92,75 +150,348 @@ impl<'a, 'b, 'c, T> Receiving<'a, 'b, 'c, T> {
             if state.is_empty() {
                 if state.is_closed() { return Err(Closed); }
                 // Hard luck, time to synchronise (and recheck)
-                state = State(spsc.atomics().state.load(Ordering::Acquire));
-                receiver.state.set(state);
+                state = spsc.refresh_receiver(cap);
+                self.state.set(state);
                 if state.is_empty() {
                     if state.is_closed() { return Err(Closed); }
                     return Ok(None);
                 }
             }
             // Still here? Fabulous, we have a message waiting for us.
-            let back = state.back();
-            // This mouthful takes the value, leaving the slot uninitialised
-            let value = unsafe { spsc.data().add(back.index(cap)).read().assume_init() };
-            // Now inform the Sender they can have this slot back.
-            let b = back.advance(receiver.cap, 1);
-            let mask = ((back.0 ^ b.0) as usize) << BITS;
-            let atomics = spsc.atomics();
-            let state = State(atomics.state.fetch_xor(mask, Ordering::Acquire) ^ mask);
-            receiver.state.set(state);
-            // Now we attempt to wake the Sender if they are not
-            // closed. There will probably be nothing here.
-            #[cfg(feature="async")]
-            if !state.is_closed() { spsc.atomics().sender.wake(); }
-            return Ok(Some(value));
+            return match self.take(&spsc, state) {
+                Ok(value) => Ok(Some(value)),
+                Err(state) if state.is_closed() => Err(Closed),
+                Err(_) => Ok(None),
+            };
         }
         Err(Closed)
     }
+
+    /// Receives a message if there is one, or registers `ctx`'s waker
+    /// to be woken when there is (or the Sender closes). This is what
+    /// [`Receiving`] does when polled, for those who would rather poll
+    /// us from a future of their own.
+    ///
+    /// Note: if you stop polling before we are ready, the waker stays
+    /// registered until we next are, or are dropped.
+    #[cfg(feature="async")]
+    pub fn poll_recv(&mut self, ctx: &mut Context) -> Poll<Result<T, Closed>> {
+        // We have no future to remember our waker in, so we register it
+        // afresh each time, and only remember that we did.
+        let ret = self.poll_receive(ctx, &mut None);
+        if ret.is_pending() {
+            self.polled = true;
+        } else if core::mem::take(&mut self.polled) {
+            self.unregister();
+        }
+        ret
+    }
+
+    /// As [`poll_recv`](Self::poll_recv), on behalf of a future.
+    /// `waker` is the one it last registered.
+    #[cfg(feature="async")]
+    fn poll_receive(&mut self, ctx: &mut Context, waker: &mut Option<Waker>) -> Poll<Result<T, Closed>> {
+        let ret = loop {
+            match self.poll_segment(ctx, waker) {
+                // The end of a segment, rather than of the channel.
+                Poll::Ready(Err(Closed)) if self.next_segment() => (),
+                // As in `receive_now`.
+                Poll::Ready(Ok(value)) => {
+                    self.next_segment();
+                    break Poll::Ready(Ok(value));
+                }
+                ret => break ret,
+            }
+        };
+        // Nobody will be polling for the waker we left.
+        if ret.is_ready() && waker.is_some() { self.unregister(); }
+        ret
+    }
+
+    /// Clears the waker we registered, if it is still there.
+    #[cfg(feature="async")]
+    fn unregister(&self) {
+        if let Some(spsc) = self.spsc.as_ref() { spsc.unregister(Side::Receiver); }
+    }
+
+    /// As [`poll_recv`](Self::poll_recv), but only from the segment we
+    /// are on.
+    #[cfg(feature="async")]
+    fn poll_segment(&mut self, ctx: &mut Context, waker: &mut Option<Waker>) -> Poll<Result<T, Closed>> {
+        if let Some(spsc) = self.spsc {
+            let cap = self.cap.get();
+            let mut state = self.state.get();
+            loop {
+                // Try to find a message without hitting the atomic.
+                if state.is_empty() {
+                    // If we're closed, we don't need to synchronise again.
+                    if state.is_closed() { return Poll::Ready(Err(Closed)); }
+                    // No? let's refresh the state then and check again
+                    state = spsc.refresh_receiver(cap);
+                    if !state.is_closed() && state.is_empty() {
+                        // Maybe the Sender is only just behind us.
+                        let ready = || {
+                            let state = spsc.load();
+                            state.is_closed() || !state.is_empty()
+                        };
+                        if self.wait.spin(ready) { state = spsc.refresh_receiver(cap); }
+                    }
+                    self.state.set(state);
+                    if state.is_empty() {
+                        if state.is_closed() { return Poll::Ready(Err(Closed)); }
+                        // Go into hibernation
+                        spsc.register(Side::Receiver, ctx.waker(), waker);
+                        // They may have sent before we registered.
+                        state = spsc.load();
+                        self.state.set(state);
+                        if state.is_empty() {
+                            if state.is_closed() { return Poll::Ready(Err(Closed)); }
+                            return Poll::Pending;
+                        }
+                    }
+                }
+                // Good news, we can receive a value (unless the Sender
+                // evicts or withdraws it first).
+                match self.take(&spsc, state) {
+                    Ok(value) => return Poll::Ready(Ok(value)),
+                    Err(s) => state = s,
+                }
+            }
+        }
+        Poll::Ready(Err(Closed))
+    }
+
+    /// Once we have drained a segment of an unbounded channel which the
+    /// Sender has sealed, moves on to the next. Returns whether we did.
+    fn next_segment(&mut self) -> bool {
+        let spsc = match self.spsc {
+            Some(spsc) => spsc,
+            None => return false,
+        };
+        let state = self.state.get();
+        if !state.is_empty() || !state.front().is_closed() { return false; }
+        let next = match spsc.next_segment() {
+            Some(next) => next,
+            None => return false,
+        };
+        #[cfg(feature="async")]
+        spsc.unregister(Side::Receiver);
+        let min = spsc.watermark(Side::Receiver);
+        unsafe { spsc.leave(); }
+        // The Sender may have resized the channel.
+        let cap = next.segment_capacity().unwrap();
+        next.set_watermark(Side::Receiver, min.min(cap));
+        self.spsc = Some(next);
+        // We may have skipped ahead (see `Holder::enter`).
+        self.state.set(next.load());
+        self.cap = self.cap.resize(cap);
+        true
+    }
+}
+
+
+
+#[cfg(feature="fd")]
+impl<'a, 'b, T, C: Capacity> std::os::unix::io::AsRawFd for Receiver<'a, 'b, T, fd::EventFds, C> {
+    /// The eventfd for this side.
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.spsc.as_ref().map_or(-1, |spsc| spsc.fd(Side::Receiver))
+    }
+}
+
+#[cfg(feature="fd")]
+impl<'a, 'b, T, C: Capacity> mio::event::Source for Receiver<'a, 'b, T, fd::EventFds, C> {
+    fn register(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest)
+                -> std::io::Result<()> {
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+        mio::unix::SourceFd(&fd).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest)
+                  -> std::io::Result<()> {
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+        mio::unix::SourceFd(&fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> std::io::Result<()> {
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+        mio::unix::SourceFd(&fd).deregister(registry)
+    }
+}
+
+unsafe impl<'a, 'b, T: Send, N: Notify, C: Capacity> Send for Receiver<'a, 'b, T, N, C> {}
+unsafe impl<'a, 'b, T: Send, N: Notify, C: Capacity> Sync for Receiver<'a, 'b, T, N, C> {}
+
+impl<'a, 'b, T, N: Notify, C: Capacity> Drop for Receiver<'a, 'b, T, N, C> {
+    fn drop(&mut self) {
+        if let Some(spsc) = self.spsc.take() {
+            // Don't leave our waker in its slot.
+            #[cfg(feature="async")]
+            spsc.unregister(Side::Receiver);
+            if spsc.is_segment() {
+                // Leave the Sender's segments behind too, freeing those
+                // it has sealed.
+                let mut segment = Some(spsc);
+                while let Some(s) = segment { segment = unsafe { s.leave() }; }
+            } else {
+                unsafe { spsc.close(Side::Receiver, R_CLOSE, self.cap.get(), self.state.get()); }
+            }
+        }
+    }
+}
+
+/// A single Receive operation that can be performed synchronously
+/// (with [`Receiving::now`]) or asynchronously (with the
+/// [`core::future::Future`] instance).
+pub struct Receiving<'a, 'b, 'c, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    receiver: Option<&'c mut Receiver<'a, 'b, T, N, C>>,
+    // The waker we last registered, if any.
+    #[cfg(feature="async")]
+    waker:    Option<Waker>,
+}
+
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Receiving<'a, 'b, 'c, T, N, C> {
+    pub fn now(mut self) -> Result<Option<T>, Closed> {
+        // Take our receiver, since we can't be called again.
+        let receiver = self.receiver.take().unwrap();
+        receiver.receive_now()
+    }
+
+    /// Receives a message, blocking the current thread until there is
+    /// one or the Sender closes.
+    ///
+    /// Note: this only truly blocks for shm channels and those whose
+    /// [`Notify`] can wait (e.g. [`Park`], or the default [`Wakers`]
+    /// with the `std` feature). Others will yield or spin.
+    pub fn wait(mut self) -> Result<T, Closed> {
+        let receiver = self.receiver.take().unwrap();
+        loop {
+            if let Some(value) = receiver.receive_now()? {
+                return Ok(value);
+            }
+            if let Some(spsc) = receiver.spsc.as_ref() {
+                let seen = receiver.state.get();
+                let moved = || spsc.load() != seen;
+                if !receiver.wait.spin(moved) { spsc.wait_receiver(seen); }
+            }
+        }
+    }
 }
 
 #[cfg(feature="async")]
-impl<'a, 'b, 'c, T> Future for Receiving<'a, 'b, 'c, T> {
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Future for Receiving<'a, 'b, 'c, T, N, C> {
     type Output = Result<T, Closed>;
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
         let this = unsafe { Pin::get_unchecked_mut(self) };
         let receiver = this.receiver.take().unwrap();
-        if let Some(spsc) = receiver.spsc.as_mut() {
-            let cap = receiver.cap;
-            let mut state = receiver.state.get();
-            // Try to find a message without hitting the atomic.
-            if state.is_empty() {
-                // If we're closed, we don't need to synchronise again.
-                if state.is_closed() { return Poll::Ready(Err(Closed)); }
-                // No? let's refresh the state then and check again
-                state = State(spsc.atomics().state.load(Ordering::Acquire));
-                receiver.state.set(state);
-                if state.is_empty() {
-                    if state.is_closed() { return Poll::Ready(Err(Closed)); }
-                    // Go into hibernation
-                    spsc.atomics().receiver.register(ctx.waker());
-                    this.receiver.replace(receiver);
-                    return Poll::Pending;
-                }
+        let ret = receiver.poll_receive(ctx, &mut this.waker);
+        if ret.is_pending() { this.receiver.replace(receiver); }
+        ret
+    }
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Drop for Receiving<'a, 'b, 'c, T, N, C> {
+    fn drop(&mut self) {
+        // Don't leave our waker in its slot.
+        if self.waker.is_none() { return; }
+        if let Some(receiver) = self.receiver.as_ref() { receiver.unregister(); }
+    }
+}
+
+/// A single receive with a Receiver it owns. See
+/// [`Receiver::into_recv`].
+#[cfg(feature="async")]
+pub struct IntoReceiving<'a, 'b, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    receiver: Option<Receiver<'a, 'b, T, N, C>>,
+    // The waker we last registered, if any.
+    waker:    Option<Waker>,
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, T, N: Notify, C: Capacity> Future for IntoReceiving<'a, 'b, T, N, C> {
+    type Output = (Receiver<'a, 'b, T, N, C>, Result<T, Closed>);
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let receiver = this.receiver.as_mut().expect("polled after completion");
+        match receiver.poll_receive(ctx, &mut this.waker) {
+            Poll::Ready(ret) => Poll::Ready((this.receiver.take().unwrap(), ret)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, T, N: Notify, C: Capacity> Drop for IntoReceiving<'a, 'b, T, N, C> {
+    fn drop(&mut self) {
+        // Don't leave our waker in its slot.
+        if self.waker.is_none() { return; }
+        if let Some(receiver) = self.receiver.as_ref() { receiver.unregister(); }
+    }
+}
+
+/// Waits for there to be a number of messages. See
+/// [`Receiver::wait_len`].
+#[cfg(feature="async")]
+pub struct WaitLen<'a, 'b, 'c, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    receiver: &'c mut Receiver<'a, 'b, T, N, C>,
+    n:        Half,
+    // The waker we last registered, if any.
+    waker:    Option<Waker>,
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Future for WaitLen<'a, 'b, 'c, T, N, C> {
+    type Output = Result<Half, Closed>;
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let (receiver, n) = (&mut *this.receiver, this.n);
+        let cap = receiver.cap.get();
+        while let Some(spsc) = receiver.spsc {
+            let ring = spsc.ring(cap);
+            let check = |state: State| {
+                let len = state.len(ring);
+                if len >= n { return Some(Ok(len)); }
+                if state.is_closed() { Some(Err(Closed)) } else { None }
+            };
+            let mut state = spsc.refresh_receiver(cap);
+            if check(state).is_none() {
+                let ready = || check(spsc.load()).is_some();
+                if receiver.wait.spin(ready) { state = spsc.refresh_receiver(cap); }
             }
-            // Good news, we can receive a value.
-            let back = state.back();
-            let value = unsafe { spsc.data().add(back.index(cap)).read().assume_init() };
-            // Now inform the other side we're done reading.
-            let b = back.advance(cap, 1);
-            let mask = ((back.0 ^ b.0) as usize) << BITS;
-            let atomics = spsc.atomics();
-            let state = State(atomics.state.fetch_xor(mask, Ordering::Acquire) ^ mask);
             receiver.state.set(state);
-            // Now we attempt to wake the Sender if they are not
-            // closed. There will probably be nothing here.
-            if !state.is_closed() { atomics.sender.wake(); }
-            return Poll::Ready(Ok(value));
+            let ret = match check(state) {
+                Some(ret) => ret,
+                None => {
+                    spsc.register(Side::Receiver, ctx.waker(), &mut this.waker);
+                    // The Sender may have sent more before we registered.
+                    let state = spsc.load();
+                    receiver.state.set(state);
+                    match check(state) {
+                        Some(ret) => ret,
+                        None => return Poll::Pending,
+                    }
+                }
+            };
+            if ret.is_err() {
+                // The end of a segment, rather than of the channel.
+                if receiver.next_segment() { continue; }
+                let state = receiver.state.get();
+                if !state.is_empty() && spsc.next_segment().is_some() { return Poll::Ready(Ok(state.len(ring))); }
+            }
+            return Poll::Ready(ret);
         }
         Poll::Ready(Err(Closed))
     }
 }
 
+#[cfg(feature="async")]
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Drop for WaitLen<'a, 'b, 'c, T, N, C> {
+    fn drop(&mut self) {
+        if self.waker.is_some() { self.receiver.unregister(); }
+    }
+}
+
 // pub struct Batch<'a, 'b, 'c, T> {
 //     receiver: Option<&'a mut Receiver<'b, 'c, T>>,
 //     state:    State,
@@ -195,7 +526,7 @@ impl<'a, 'b, 'c, T> Future for Receiving<'a, 'b, 'c, T> {
 //                     (&mut *spsc.buffer.get())[back.position()].as_mut_ptr().read()
 //                 };
 //                 // Update our local version of the state.
-//                 self.state = state.with_back(back.advance(receiver.cap, 1));
+//                 self.state = state.with_back(back.advance(receiver.cap.get(), 1));
 //                 return Some(value);
 //             }
 //         }
@@ -0,0 +1,64 @@
+//! Channels which hold nothing, handing each message straight over.
+//!
+//! Buffering can hide backpressure. A channel from
+//! [`spsc`](crate::spsc()) with a capacity of 0 is a rendezvous: a
+//! send only completes once the Receiver has taken the message, so the
+//! Sender is never more than one message ahead. [`Sending::now`] only
+//! succeeds if the Receiver takes it at once, so it is the waiting
+//! forms which are useful here:
+//!
+//! ```
+//! use async_spsc::spsc;
+//!
+//! let (mut sender, mut receiver) = spsc::<i32>(0);
+//! assert_eq!(0, sender.capacity());
+//! let t = std::thread::spawn(move || {
+//!     for i in 0..3 { sender.send(i).wait().unwrap(); }
+//! });
+//! for i in 0..3 { assert_eq!(Ok(i), receiver.receive().wait()); }
+//! t.join().unwrap();
+//! ```
+//!
+//! Underneath is a ring of two slots which holds at most one message.
+//! The Receiver claims it as a lossy Receiver does (see [`lossy`]),
+//! so a Sender which gives up waiting can take it back if it has not
+//! been, and the other slot is spare for the next while the Receiver
+//! is still reading it.
+use crate::*;
+use crate::lossy::READING;
+
+/// The ring a rendezvous channel uses.
+#[cfg(feature="alloc")]
+pub(crate) const RENDEZVOUS_RING: Half = 2;
+
+impl<'a, 'b, T, N: Notify, A: AtomicState> Holder<'a, 'b, T, N, A> {
+
+    /// Whether a send completes only once the Receiver has taken it.
+    #[inline(always)]
+    pub(crate) fn is_rendezvous(&self) -> bool {
+        #[cfg(feature="alloc")]
+        return matches!(self, Holder::Rendezvous(_));
+        #[cfg(not(feature="alloc"))]
+        false
+    }
+
+    /// Takes back the message the Sender last sent into a ring of `cap`
+    /// slots, unless the Receiver has claimed it. Returns it and the
+    /// state it was taken back from, or the state if it was claimed.
+    pub(crate) fn withdraw(&self, cap: Half) -> Result<(T, State), State> {
+        let atomic = self.state();
+        let mut seen = atomic.load_state(Ordering::Acquire);
+        loop {
+            let state = State(seen.0 & !READING);
+            if state.is_empty() { return Err(state); }
+            let front = state.front();
+            let f = front.advance(cap, 2 * cap - 1);
+            let next = State(seen.0 ^ (front.0 ^ f.0) as Word);
+            match atomic.swap_state(seen, next, Ordering::AcqRel) {
+                // The Receiver can no longer claim it, so it is ours.
+                Ok(_) => return Ok((unsafe { self.slot(f, cap).read().assume_init() }, state)),
+                Err(s) => seen = s,
+            }
+        }
+    }
+}
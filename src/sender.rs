@@ -1,39 +1,494 @@
 use crate::*;
 use core::cell::Cell;
 
-pub struct Sender<'a, 'b, T> {
-    spsc:  Option<Holder<'a, 'b, T>>,
-    state: Cell<State>,
-    cap:   Half,
+pub struct Sender<'a, 'b, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    spsc:    Option<Holder<'a, 'b, T, N, C::Atomic>>,
+    state:   Cell<State>,
+    cap:     C,
+    wait:    WaitStrategy,
+    // How many sent messages we have yet to publish, and how many we
+    // may hold back.
+    pending: Cell<Half>,
+    defer:   Half,
+    // Whether `poll_ready` has left a waker registered.
+    #[cfg(feature="async")]
+    polled:  bool,
 }
 
-impl<'a, 'b, T> Sender<'a, 'b, T> {
+impl<'a, 'b, T, N: Notify, C: Capacity> Sender<'a, 'b, T, N, C> {
 
-    pub(super) fn new(spsc: Holder<'a, 'b, T>, state: State, cap: Half) -> Self {
-        Sender { spsc: Some(spsc), state: Cell::new(state), cap }
+    pub(super) fn new(spsc: Holder<'a, 'b, T, N, C::Atomic>, state: State, cap: C) -> Self {
+        Sender {
+            spsc: Some(spsc),
+            state: Cell::new(state),
+            cap,
+            wait: WaitStrategy::Park,
+            pending: Cell::new(0),
+            defer: 1,
+            #[cfg(feature="async")]
+            polled: false,
+        }
     }
 
     /// Indicates how many send slots are known to be available.
     ///
     /// Note: this checks our local cache of the state, so the true
     /// figure may be greater. We will find out when we next send.
-    pub fn space(&self) -> Half { self.state.get().space(self.cap) }
+    pub fn space(&self) -> Half { self.limit() - self.state.get().len(self.ring()) }
 
     /// Indicates whether we believe there to be no space left to send.
     ///
     /// Note: this checks our local cache of the state, so the true
     /// figure may be greater. We will find out when we next send.
-    pub fn is_full(&self) -> bool { self.state.get().is_full(self.cap) }
+    pub fn is_full(&self) -> bool { self.state.get().len(self.ring()) >= self.limit() }
 
     /// Indicates whether the channel is empty.
-    pub fn is_empty(&self) -> bool { self.state.get().is_full(self.cap) }
+    pub fn is_empty(&self) -> bool { self.state.get().len(self.ring()) == 0 }
 
     /// Indicates the capacity of the channel, the maximum number of
-    /// messages that can be in flight at a time.
-    pub fn capacity(&self) -> Half { self.cap }
+    /// messages that can be in flight at a time. A rendezvous has none.
+    pub fn capacity(&self) -> Half {
+        if self.spsc.as_ref().is_some_and(|spsc| spsc.is_rendezvous()) { return 0; }
+        self.limit()
+    }
+
+    /// How many messages may be in flight in our ring. See
+    /// `Holder::limit`.
+    #[inline(always)]
+    fn limit(&self) -> Half {
+        self.spsc.as_ref().map_or(self.cap.get(), |spsc| spsc.limit(self.cap.get()))
+    }
+
+    /// The size of ring our positions go round. See `Holder::ring`.
+    #[inline(always)]
+    fn ring(&self) -> Half {
+        self.spsc.as_ref().map_or(self.cap.get(), |spsc| spsc.ring(self.cap.get()))
+    }
+
+    /// Sets what we do when we find the channel full. See [`WaitStrategy`].
+    pub fn set_wait_strategy(&mut self, wait: WaitStrategy) { self.wait = wait; }
+
+    /// When we are waiting for space, only wake us once at least `min`
+    /// slots are free (or the Receiver closes), rather than as soon as
+    /// one is. The default is 1.
+    ///
+    /// Note: will panic if `min` is 0 or more than the capacity. Has no
+    /// effect on shm channels.
+    pub fn set_min_space(&mut self, min: Half) {
+        assert!(min > 0 && min <= self.limit());
+        if let Some(spsc) = self.spsc.as_ref() { spsc.set_watermark(Side::Sender, min); }
+    }
+
+    /// Holds sent messages back from the Receiver until `n` have built
+    /// up or we [`flush`](Self::flush), so that we only have to update
+    /// the shared state once for all of them. The default is 1, which
+    /// publishes every message as it is sent.
+    ///
+    /// Note: a message we hold back will not notice that the Receiver
+    /// has closed. Such messages may be [`reclaim`](Self::reclaim)ed.
+    ///
+    /// Note: will panic if `n` is 0 or more than the capacity.
+    pub fn set_flush_threshold(&mut self, n: Half) {
+        assert!(n > 0 && n <= self.limit());
+        self.defer = n;
+    }
+
+    /// Publishes any messages we have held back (see
+    /// [`set_flush_threshold`](Self::set_flush_threshold)) and wakes
+    /// the Receiver if it is waiting for any, regardless of its
+    /// minimum batch. Fails if the Receiver has closed.
+    pub fn flush(&mut self) -> Result<(), Closed> {
+        let spsc = self.spsc.ok_or(Closed)?;
+        if self.state.get().is_closed() { return Err(Closed); }
+        let state = self.publish(&spsc, true);
+        if state.is_closed() { Err(Closed) } else { Ok(()) }
+    }
+
+    /// Moves a resizable channel (see [`spsc_resizable`]) on to a ring
+    /// of `capacity` slots, so there is room for more. Messages already
+    /// sent stay in the old ring for the Receiver to drain before it
+    /// moves on too, and take up room in the new one until it does.
+    /// Fails if the channel is not resizable or the Receiver has closed.
+    ///
+    /// Note: will panic if `capacity` is less than the current one or
+    /// too large.
+    #[cfg(feature="alloc")]
+    pub fn grow(&mut self, capacity: Half) -> Result<(), ResizeError> {
+        assert!(capacity >= self.cap.get());
+        self.resize(capacity)
+    }
+
+    /// As [`grow`](Self::grow), but to a ring of fewer slots. Fails
+    /// with [`ResizeError::Full`] if more than `capacity` messages are
+    /// still in flight, so wait for the Receiver to take some and try
+    /// again.
+    ///
+    /// Note: will panic if `capacity` is 0 or more than the current one.
+    #[cfg(feature="alloc")]
+    pub fn shrink(&mut self, capacity: Half) -> Result<(), ResizeError> {
+        assert!(capacity > 0 && capacity <= self.cap.get());
+        self.resize(capacity)
+    }
+
+    #[cfg(feature="alloc")]
+    fn resize(&mut self, capacity: Half) -> Result<(), ResizeError> {
+        assert!(capacity <= MAX_CAPACITY);
+        let spsc = self.spsc.ok_or(ResizeError::Closed)?;
+        if !spsc.is_segment() || spsc.is_unbounded() { return Err(ResizeError::NotResizable); }
+        if self.state.get().is_closed() { return Err(ResizeError::Closed); }
+        if capacity == self.cap.get() { return Ok(()); }
+        // Those the Receiver has yet to take, including any we held back.
+        let cap = self.cap.get();
+        let state = spsc.load();
+        if state.is_closed() { return Err(ResizeError::Closed); }
+        let in_flight = state.with_front(self.state.get().front()).len(cap);
+        if in_flight > capacity { return Err(ResizeError::Full); }
+        self.seal(spsc, capacity, in_flight).map_err(|Closed| ResizeError::Closed)
+    }
+
+    /// Once the Receiver has closed, takes back the oldest message it
+    /// did not receive. Returns `None` while the Receiver is open or
+    /// when there is nothing left.
+    pub fn reclaim(&mut self) -> Option<T> {
+        let spsc = self.spsc?;
+        let mut state = self.state.get();
+        if !state.back().is_closed() {
+            state = spsc.load();
+            if !state.back().is_closed() { return None; }
+            // Count the messages we held back among those to reclaim.
+            if self.pending.get() > 0 { state = self.publish(&spsc, false); }
+            self.state.set(state);
+        }
+        if state.is_empty() { return None; }
+        // The Receiver is gone, so the messages are ours alone and we
+        // may advance the back on its behalf.
+        let back = HalfState(state.back().position());
+        let value = unsafe { spsc.slot(back, self.cap.get()).read().assume_init() };
+        let b = back.advance(self.ring(), 1);
+        spsc.update(Side::Receiver, ((back.0 ^ b.0) as Word) << BITS);
+        self.state.set(state.with_back(b.close()));
+        Some(value)
+    }
+
+    pub fn send<'c>(&'c mut self, value: T) -> Sending<'c, 'a, 'b, T, N, C> {
+        Sending {
+            sender: Some(self),
+            value:  Some(value),
+            #[cfg(feature="async")]
+            flags:  0,
+            #[cfg(feature="async")]
+            waker:  None,
+        }
+    }
+
+    /// As [`send`](Self::send), but the future owns us rather than
+    /// borrowing us, and hands us back with the result. It can thus
+    /// be stored or spawned apart from wherever we came from.
+    #[cfg(feature="async")]
+    pub fn into_send(self, value: T) -> IntoSending<'a, 'b, T, N, C> {
+        IntoSending { sender: Some(self), value: Some(value), flags: 0, waker: None }
+    }
+
+    /// Sends a message if there is space, without waiting. This is what
+    /// [`Sending::now`] does, so on a rendezvous channel it fails
+    /// unless the Receiver takes the message at once.
+    pub fn try_send(&mut self, value: T) -> Result<(), SendError<T>> {
+        self.send_now(value)?;
+        self.withdraw().map_or(Ok(()), Err)
+    }
+
+    /// Resolves once there is space to [`try_send`](Self::try_send) a
+    /// message, registering `ctx`'s waker until there is. Fails if the
+    /// Receiver has closed. Lossy and unbounded channels always have
+    /// space.
+    ///
+    /// Note: on a rendezvous channel, this only means the last message
+    /// has been taken, not that the next will be at once. If you stop
+    /// polling before we are ready, the waker stays registered until
+    /// we next are, or are dropped.
+    #[cfg(feature="async")]
+    pub fn poll_ready(&mut self, ctx: &mut Context) -> Poll<Result<(), Closed>> {
+        let spsc = match self.spsc {
+            Some(spsc) => spsc,
+            None => return Poll::Ready(Err(Closed)),
+        };
+        if spsc.is_lossy() || spsc.is_unbounded() {
+            let closed = self.state.get().is_closed() || spsc.load().is_closed();
+            return Poll::Ready(if closed { Err(Closed) } else { Ok(()) });
+        }
+        // We have no future to remember our waker in, so we register
+        // it afresh each time, and only remember that we did.
+        let ret = self.poll_space(ctx, 1, &mut None);
+        if ret.is_pending() {
+            self.polled = true;
+        } else if core::mem::take(&mut self.polled) {
+            self.unregister();
+        }
+        ret.map(|ret| ret.map(drop))
+    }
+
+    /// Returns a future which resolves to the free space once there
+    /// are at least `n` free slots, without sending anything. Fails if
+    /// the Receiver closes first.
+    ///
+    /// Note: will panic if `n` is 0 or more than the capacity.
+    #[cfg(feature="async")]
+    pub fn wait_space<'c>(&'c mut self, n: Half) -> WaitSpace<'c, 'a, 'b, T, N, C> {
+        assert!(n > 0 && n <= self.limit());
+        WaitSpace { sender: self, n, waker: None }
+    }
+
+    /// The mask which would advance the front of the shared state to
+    /// cover the messages we have not published.
+    #[inline(always)]
+    fn pending_mask(&self) -> Word {
+        let pending = self.pending.get();
+        if pending == 0 { return 0; }
+        let front = self.state.get().front();
+        let ring = self.ring();
+        (front.advance(ring, 2 * ring - pending).0 ^ front.0) as Word
+    }
+
+    /// Publishes the messages we have not yet, returning the new state.
+    /// If `flush`, we will also check the state and wake the Receiver
+    /// when there is nothing to publish.
+    #[inline(always)]
+    fn publish(&self, spsc: &Holder<'a, 'b, T, N, C::Atomic>, flush: bool) -> State {
+        let mask = self.pending_mask();
+        if mask == 0 && !flush { return self.state.get(); }
+        self.pending.set(0);
+        let state = if mask == 0 {
+            spsc.load()
+        } else {
+            State(spsc.advance(Side::Sender, mask, self.state.get()).0 ^ mask)
+        };
+        self.state.set(state);
+        // Before we go, let the receiver know there's a message.
+        if !state.is_closed() { spsc.wake_receiver(State(state.0 ^ mask), state, self.cap.get(), flush); }
+        state
+    }
+
+    /// Seals our segment of a chain, publishing any messages we held
+    /// back, and moves on to a new one of `capacity` slots, `carried` of
+    /// which stand for messages still in flight. Fails if the Receiver
+    /// left before it could find the new one.
+    #[cfg(feature="alloc")]
+    fn seal(&mut self, spsc: Holder<'a, 'b, T, N, C::Atomic>, capacity: Half, carried: Half) -> Result<(), Closed> {
+        let cap = self.cap.get();
+        let next = spsc.link(capacity, carried);
+        #[cfg(feature="async")]
+        spsc.unregister(Side::Sender);
+        let mask = self.pending_mask() | S_CLOSE;
+        self.pending.set(0);
+        let prev = unsafe { spsc.close(Side::Sender, mask, cap, self.state.get()) };
+        if prev.back().is_closed() {
+            // It never saw the link, so the new one is ours alone.
+            unsafe { next.cleanup(capacity, State(0)) };
+            self.spsc = None;
+            return Err(Closed);
+        }
+        self.spsc = Some(next);
+        self.state.set(next.load());
+        self.cap = self.cap.resize(capacity);
+        self.defer = self.defer.min(capacity);
+        Ok(())
+    }
+
+    /// On a rendezvous channel, takes back the message we just sent if
+    /// the Receiver has yet to claim it, returning it in the error it
+    /// should be sent back with.
+    fn withdraw(&mut self) -> Option<SendError<T>> {
+        let spsc = self.spsc.filter(|spsc| spsc.is_rendezvous())?;
+        let (value, state) = spsc.withdraw(self.cap.get()).ok()?;
+        self.state.set(spsc.load());
+        let kind = if state.back().is_closed() { SendErrorKind::Closed } else { SendErrorKind::Full };
+        Some(SendError { kind, value })
+    }
 
-    pub fn send<'c>(&'c mut self, value: T) -> Sending<'c, 'a, 'b, T> {
-        Sending { sender: Some(self),value: Some(value), flags: 0 }
+    /// Whether the Receiver has taken the message we just sent into a
+    /// rendezvous channel, or the error if it closed without doing so.
+    /// Sends into any other channel are done once they are sent.
+    fn handed_over(&mut self) -> Option<Result<(), SendError<T>>> {
+        let spsc = match self.spsc {
+            Some(spsc) if spsc.is_rendezvous() => spsc,
+            _ => return Some(Ok(())),
+        };
+        let state = spsc.load();
+        self.state.set(state);
+        if state.is_empty() { return Some(Ok(())); }
+        if !state.back().is_closed() { return None; }
+        // It may yet claim it before we take it back.
+        Some(self.withdraw().map_or(Ok(()), Err))
+    }
+
+    /// Resolves to the free space once there are at least `n` free
+    /// slots, registering `ctx`'s waker until there are. `waker` is the
+    /// one we last registered.
+    #[cfg(feature="async")]
+    fn poll_space(&mut self, ctx: &mut Context, n: Half, waker: &mut Option<Waker>) -> Poll<Result<Half, Closed>> {
+        let spsc = match self.spsc {
+            Some(spsc) => spsc,
+            None => return Poll::Ready(Err(Closed)),
+        };
+        let (cap, limit, ring) = (self.cap.get(), self.limit(), self.ring());
+        let check = |state: State| {
+            if state.is_closed() { return Some(Err(Closed)); }
+            let space = limit - state.len(ring);
+            if space >= n { Some(Ok(space)) } else { None }
+        };
+        // The Receiver can't make space for messages it can't see.
+        self.publish(&spsc, false);
+        let mut state = spsc.refresh_sender(cap);
+        if check(state).is_none() {
+            let ready = || check(spsc.load()).is_some();
+            if self.wait.spin(ready) { state = spsc.refresh_sender(cap); }
+        }
+        self.state.set(state);
+        if let Some(ret) = check(state) { return Poll::Ready(ret); }
+        spsc.register(Side::Sender, ctx.waker(), waker);
+        // The Receiver may have made space before we registered.
+        let state = spsc.load();
+        self.state.set(state);
+        check(state).map_or(Poll::Pending, Poll::Ready)
+    }
+
+    /// Sends `value` on behalf of a future, taking it out once it is
+    /// sent. `flags` and `waker` are the future's own, to be passed
+    /// back in when it is polled again.
+    #[cfg(feature="async")]
+    fn poll_send(&mut self, ctx: &mut Context, value: &mut Option<T>, flags: &mut u8, waker: &mut Option<Waker>)
+                 -> Poll<Result<(), SendError<T>>> {
+        let ret = self.send_or_register(ctx, value, flags, waker);
+        // Nobody will be polling for the waker we left.
+        if ret.is_ready() && waker.is_some() { self.unregister(); }
+        ret
+    }
+
+    /// As [`poll_send`](Self::poll_send), but leaves whatever waker we
+    /// registered.
+    #[cfg(feature="async")]
+    fn send_or_register(&mut self, ctx: &mut Context, value: &mut Option<T>, flags: &mut u8,
+                        waker: &mut Option<Waker>) -> Poll<Result<(), SendError<T>>> {
+        // Unless we have already sent it, and are waiting for the
+        // Receiver of a rendezvous to take it.
+        if let Some(mut v) = value.take() {
+            loop {
+                match self.send_now(v) {
+                    Err(SendError { kind: SendErrorKind::Full, value: u }) => v = u,
+                    Ok(()) => break,
+                    r => return Poll::Ready(r),
+                }
+                match self.poll_space(ctx, 1, waker) {
+                    Poll::Ready(Ok(_)) => (),
+                    Poll::Ready(Err(Closed)) => return Poll::Ready(closed(v)),
+                    Poll::Pending => {
+                        // We'll have to wait. We'll also have to put it back.
+                        *flags |= WAITING;
+                        *value = Some(v);
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+        // Our waker may have been used up making space, so we register
+        // it again if we must wait for the handover.
+        let mut registered = false;
+        loop {
+            if let Some(ret) = self.handed_over() { return Poll::Ready(ret); }
+            if registered { return Poll::Pending; }
+            // We only wait for rendezvous channels, which we hold.
+            let spsc = self.spsc.unwrap();
+            let ready = |state: State| state.is_closed() || state.is_empty();
+            if self.wait.spin(|| ready(spsc.load())) { continue; }
+            *flags |= WAITING;
+            spsc.register(Side::Sender, ctx.waker(), waker);
+            registered = true;
+        }
+    }
+
+    /// Clears the waker we registered, if it is still there.
+    #[cfg(feature="async")]
+    fn unregister(&self) {
+        if let Some(spsc) = self.spsc.as_ref() { spsc.unregister(Side::Sender); }
+    }
+
+    /// Cleans up after a future which was dropped before its send was
+    /// done. `sent` is whether it had already sent its value.
+    #[cfg(feature="async")]
+    fn abandon_send(&mut self, sent: bool, flags: u8) {
+        if (flags & WAITING) != 0 {
+            // We left a waker we should probably clear up
+            self.unregister();
+        }
+        // We gave up waiting for the Receiver of a rendezvous to
+        // take our message, so take it back if it hasn't.
+        if sent { drop(self.withdraw()); }
+    }
+
+    /// Sends a message if there is space, without waiting.
+    fn send_now(&mut self, value: T) -> Result<(), SendError<T>> {
+        let spsc = match self.spsc {
+            Some(spsc) => spsc,
+            None => return closed(value),
+        };
+        let (cap, limit) = (self.cap.get(), self.limit());
+        let ring = spsc.ring(cap);
+        let mut state = self.state.get();
+        // We do nothing if we're closed.
+        if state.is_closed() { return closed(value); }
+        if state.len(ring) >= limit {
+            // The Receiver can't make space for messages it can't see.
+            state = self.publish(&spsc, false);
+            if !state.is_closed() && state.len(ring) >= limit {
+                // The Receiver may have cleared space since the cache
+                // was last updated; refresh and recheck.
+                state = spsc.refresh_sender(cap);
+                self.state.set(state);
+            }
+            if state.is_closed() { return closed(value); }
+            if state.len(ring) >= limit {
+                #[cfg(feature="alloc")]
+                if spsc.is_unbounded() {
+                    return match self.seal(spsc, cap, 0) {
+                        Ok(()) => self.send_now(value),
+                        Err(Closed) => closed(value),
+                    };
+                }
+                if !spsc.is_lossy() { return full(value); }
+                match spsc.evict(cap, limit) {
+                    Some(s) => state = s,
+                    None => {
+                        // The Receiver is reading the oldest, so the
+                        // newest goes instead.
+                        spsc.count_dropped();
+                        return Ok(());
+                    }
+                }
+                self.state.set(state);
+                if state.is_closed() { return closed(value); }
+            }
+        }
+        // Still here? Cool, we can write the value now.
+        let s = state.front();
+        unsafe { spsc.slot(s, cap).write(MaybeUninit::new(value)) };
+        self.state.set(state.with_front(s.advance(ring, 1)));
+        let pending = self.pending.get() + 1;
+        self.pending.set(pending);
+        if pending < self.defer { return Ok(()); }
+        // Update the atomic with our advance.
+        let state = self.publish(&spsc, false);
+        if state.is_closed() {
+            // Oh. Well we need our item back for the SendError.
+            let value = unsafe { spsc.slot(s, cap).read().assume_init() };
+            // We already committed our advance. To avoid double
+            // freeing, we have to wind it back, both in our local
+            // cache and for whichever side cleans up from the atomic.
+            // The Receiver is gone, so it can't have seen it.
+            spsc.update(Side::Sender, (s.0 ^ s.advance(ring, 1).0) as Word);
+            self.state.set(state.with_front(s));
+            return closed(value);
+        }
+        Ok(())
     }
 
     // pub fn batch<'c>(&'c mut self) -> Batch<'c, 'a, 'b, T> {
@@ -42,33 +497,59 @@ impl<'a, 'b, T> Sender<'a, 'b, T> {
     // }
 
 }
-impl<'a, 'b, T> Drop for Sender<'a, 'b, T> {
+impl<'a, 'b, T, N: Notify, C: Capacity> Drop for Sender<'a, 'b, T, N, C> {
     fn drop(&mut self) {
         if let Some(spsc) = self.spsc.take() {
-            let state = self.state.get();
-            if state.is_closed() {
-                unsafe { spsc.cleanup(self.cap, state); }
-                return;
-            }
-            let atomics = unsafe { &*spsc.atomics() };
-            let state = State(atomics.state.fetch_xor(S_CLOSE, Ordering::AcqRel));
-            if state.is_closed() {
-                unsafe { spsc.cleanup(self.cap, state); }
-            } else {
-                atomics.receiver.wake();
-            }
+            // Don't leave our waker in its slot.
+            #[cfg(feature="async")]
+            spsc.unregister(Side::Sender);
+            // Publish anything we held back as we close.
+            let mask = self.pending_mask() | S_CLOSE;
+            unsafe { spsc.close(Side::Sender, mask, self.cap.get(), self.state.get()); }
         }
     }
 }
 
-unsafe impl<'a, 'b, T: Send> Send for Sender<'a, 'b, T> {}
-unsafe impl<'a, 'b, T: Send> Sync for Sender<'a, 'b, T> {}
+#[cfg(feature="fd")]
+impl<'a, 'b, T, C: Capacity> std::os::unix::io::AsRawFd for Sender<'a, 'b, T, fd::EventFds, C> {
+    /// The eventfd for this side.
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.spsc.as_ref().map_or(-1, |spsc| spsc.fd(Side::Sender))
+    }
+}
+
+#[cfg(feature="fd")]
+impl<'a, 'b, T, C: Capacity> mio::event::Source for Sender<'a, 'b, T, fd::EventFds, C> {
+    fn register(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest)
+                -> std::io::Result<()> {
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+        mio::unix::SourceFd(&fd).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &mio::Registry, token: mio::Token, interests: mio::Interest)
+                  -> std::io::Result<()> {
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+        mio::unix::SourceFd(&fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> std::io::Result<()> {
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(self);
+        mio::unix::SourceFd(&fd).deregister(registry)
+    }
+}
+
+unsafe impl<'a, 'b, T: Send, N: Notify, C: Capacity> Send for Sender<'a, 'b, T, N, C> {}
+unsafe impl<'a, 'b, T: Send, N: Notify, C: Capacity> Sync for Sender<'a, 'b, T, N, C> {}
 
 /// Sends a single message.
-pub struct Sending<'a, 'b, 'c, T> {
-    sender: Option<&'a mut Sender<'b, 'c, T>>,
+pub struct Sending<'a, 'b, 'c, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    sender: Option<&'a mut Sender<'b, 'c, T, N, C>>,
     value:  Option<T>,
+    #[cfg(feature="async")]
     flags:  u8,
+    // The waker we last registered, if any.
+    #[cfg(feature="async")]
+    waker:  Option<Waker>,
 }
 
 fn closed<T>(value: T) -> Result<(), SendError<T>> {
@@ -79,117 +560,128 @@ fn full<T>(value: T) -> Result<(), SendError<T>> {
     Err(SendError { kind: SendErrorKind::Full, value })
 }
 
-impl<'a, 'b, 'c, T> Sending<'a, 'b, 'c, T> {
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Sending<'a, 'b, 'c, T, N, C> {
+    /// Sends the message if there is space, without waiting. On a
+    /// rendezvous channel, fails unless the Receiver takes it at once.
     pub fn now(mut self) -> Result<(), SendError<T>> {
         let sender = self.sender.take().unwrap();
-        let value = self.value.take().unwrap();
-        if let Some(spsc) = sender.spsc.as_mut() {
-            let cap = sender.cap;
-            let mut state = sender.state.get();
-            // We do nothing if we're closed.
-            if state.is_closed() { return closed(value); }
-            if state.is_full(cap) {
-                // The Receiver may have cleared space since the cache
-                // was last updated; refresh and recheck.
-                state = State(unsafe { &*spsc.atomics() }.state.load(Ordering::Acquire));
-                sender.state.set(state);
-                if state.is_closed() { return closed(value); }
-                if state.is_full(cap) { return full(value); }
-            }
-            // Still here? Cool, we can write the value now.
-            let s = state.front();
-            unsafe { spsc.data().add(s.index(cap)).write(MaybeUninit::new(value)) };
-            // Update the atomic with our advance.
-            let mask = (s.0 ^ s.advance(cap, 1).0) as usize;
-            let atomics = unsafe { &* spsc.atomics() };
-            let state2 = State(atomics.state.fetch_xor(mask, Ordering::Acquire) ^ mask);
-            sender.state.set(state2);
-            if state2.is_closed() {
-                // Oh. Well we need our item back for the SendError.
-                let value = unsafe { spsc.data().add(s.index(cap)).read().assume_init() };
-                // We already committed our advance. To avoid double
-                // freeing, we have to wind back the sender's local
-                // cache of the state in lieu of an atomic op.
-                sender.state.set(State((state.0 & BACK) | s.0 as usize));
-                return closed(value);
+        sender.try_send(self.value.take().unwrap())
+    }
+
+    /// Sends the message, blocking the current thread until there is
+    /// space (or, on a rendezvous channel, until the Receiver takes it)
+    /// or the Receiver closes.
+    ///
+    /// Note: this only truly blocks for shm channels and those whose
+    /// [`Notify`] can wait (e.g. [`Park`], or the default [`Wakers`]
+    /// with the `std` feature). Others will yield or spin.
+    pub fn wait(mut self) -> Result<(), SendError<T>> {
+        let sender = self.sender.take().unwrap();
+        let mut value = self.value.take().unwrap();
+        loop {
+            match sender.send_now(value) {
+                Err(SendError { kind: SendErrorKind::Full, value: v }) => {
+                    value = v;
+                    if let Some(spsc) = sender.spsc.as_ref() {
+                        let seen = sender.state.get();
+                        let moved = || spsc.load() != seen;
+                        if !sender.wait.spin(moved) { spsc.wait_sender(seen); }
+                    }
+                }
+                Ok(()) => break,
+                r => return r,
             }
-            // Before we go, let the receiver know there's a message.
-            #[cfg(feature="async")]
-            atomics.receiver.wake();
-            return Ok(());
         }
-        closed(value)
+        loop {
+            if let Some(ret) = sender.handed_over() { return ret; }
+            // We only wait for rendezvous channels, which we hold.
+            let spsc = sender.spsc.unwrap();
+            let seen = sender.state.get();
+            let moved = || spsc.load() != seen;
+            if !sender.wait.spin(moved) { spsc.wait_sender(seen); }
+        }
     }
 }
 
 #[cfg(feature="async")]
-impl<'a, 'b, 'c, T> Future for Sending<'a, 'b, 'c, T> {
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Future for Sending<'a, 'b, 'c, T, N, C> {
     type Output = Result<(), SendError<T>>;
     fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
         let this = unsafe { Pin::get_unchecked_mut(self) };
         let sender = this.sender.take().unwrap();
-        let value = this.value.take().unwrap();
-        if let Some(spsc) = sender.spsc.as_mut() {
-            let cap = sender.cap;
-            let mut state = sender.state.get();
-            // First we must check we're not closed.
-            if state.is_closed() { return Poll::Ready(closed(value)); }
-            // Try to find space without hitting the atomic.
-            if state.is_full(cap) {
-                let atomics = unsafe { &*spsc.atomics() };
-                state = State(atomics.state.load(Ordering::Acquire));
-                sender.state.set(state);
-                // We have to check again because of that refresh.
-                if state.is_closed() { return Poll::Ready(closed(value)); }
-                if state.is_full(cap) {
-                    // We'll have to wait.
-                    this.flags |= WAITING;
-                    atomics.sender.register(ctx.waker());
-                    // We'll also have to put ourselves back.
-                    this.sender.replace(sender);
-                    this.value.replace(value);
-                    return Poll::Pending
-                }
-            }
-            // Still here? Cool, we can write the value now.
-            let s = state.front();
-            unsafe { spsc.data().add(s.index(cap)).write(MaybeUninit::new(value)) };
-            // Update the atomic with our advance.
-            let mask = (s.0 ^ s.advance(cap, 1).0) as usize;
-            let atomics = unsafe { &*spsc.atomics() };
-            let state2 = State(atomics.state.fetch_xor(mask, Ordering::Acquire) ^ mask);
-            sender.state.set(state2);
-            if state2.is_closed() {
-                // Oh. Well we need our item back for the SendError.
-                let value = unsafe { spsc.data().add(s.index(cap)).read().assume_init() };
-                // We already committed our advance. To avoid double
-                // freeing, we have to wind back the sender's local
-                // cache of the state in lieu of an atomic op.
-                sender.state.set(State((state.0 & BACK) | s.0 as usize));
-                return Poll::Ready(closed(value));
-            }
-            // Before we go, let the receiver know there's a message.
-            atomics.receiver.wake();
-            return Poll::Ready(Ok(()));
-        }
-        Poll::Ready(closed(value))
+        let ret = sender.poll_send(ctx, &mut this.value, &mut this.flags, &mut this.waker);
+        if ret.is_pending() { this.sender.replace(sender); }
+        ret
     }
 }
 
-impl<'a, 'b, 'c, T> Drop for Sending<'a, 'b, 'c, T> {
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Drop for Sending<'a, 'b, 'c, T, N, C> {
     fn drop(&mut self) {
-        if let Some(sender) = self.sender.take() {
-            if (self.flags & WAITING) != 0 {
-                // We left a waker we should probably clear up
-                sender.spsc.as_mut().map(|r| unsafe { &*r.atomics() }.sender.take());
-            }
+        #[cfg(feature="async")]
+        if let Some(sender) = self.sender.take() { sender.abandon_send(self.value.is_none(), self.flags); }
+    }
+}
+
+/// Sends a single message with a Sender it owns. See
+/// [`Sender::into_send`].
+#[cfg(feature="async")]
+pub struct IntoSending<'a, 'b, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    sender: Option<Sender<'a, 'b, T, N, C>>,
+    value:  Option<T>,
+    flags:  u8,
+    // The waker we last registered, if any.
+    waker:  Option<Waker>,
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, T, N: Notify, C: Capacity> Future for IntoSending<'a, 'b, T, N, C> {
+    type Output = (Sender<'a, 'b, T, N, C>, Result<(), SendError<T>>);
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        let sender = this.sender.as_mut().expect("polled after completion");
+        match sender.poll_send(ctx, &mut this.value, &mut this.flags, &mut this.waker) {
+            Poll::Ready(ret) => Poll::Ready((this.sender.take().unwrap(), ret)),
+            Poll::Pending => Poll::Pending,
         }
     }
 }
 
+#[cfg(feature="async")]
+impl<'a, 'b, T, N: Notify, C: Capacity> Drop for IntoSending<'a, 'b, T, N, C> {
+    fn drop(&mut self) {
+        if let Some(sender) = self.sender.as_mut() { sender.abandon_send(self.value.is_none(), self.flags); }
+    }
+}
+
+/// Waits for there to be space for a number of messages. See
+/// [`Sender::wait_space`].
+#[cfg(feature="async")]
+pub struct WaitSpace<'a, 'b, 'c, T, N: Notify = DefaultNotify, C: Capacity = Half> {
+    sender: &'a mut Sender<'b, 'c, T, N, C>,
+    n:      Half,
+    // The waker we last registered, if any.
+    waker:  Option<Waker>,
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Future for WaitSpace<'a, 'b, 'c, T, N, C> {
+    type Output = Result<Half, Closed>;
+    fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { Pin::get_unchecked_mut(self) };
+        this.sender.poll_space(ctx, this.n, &mut this.waker)
+    }
+}
+
+#[cfg(feature="async")]
+impl<'a, 'b, 'c, T, N: Notify, C: Capacity> Drop for WaitSpace<'a, 'b, 'c, T, N, C> {
+    fn drop(&mut self) {
+        if self.waker.is_some() { self.sender.unregister(); }
+    }
+}
+
 // /// 
 // pub struct Batch<'a, 'b, 'c, T> {
-//     sender: Option<&'a mut Sender<'b, 'c, T>>,
+//     sender: Option<&'a mut Sender<'b, 'c, T, N>>,
 //     state: State,
 // }
 
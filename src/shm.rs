@@ -0,0 +1,649 @@
+//! Channels between processes, backed by shared memory (Linux only).
+//!
+//! The state word and the ring buffer live in a memory region that
+//! both processes map. One process creates the region and the other
+//! opens it, then each claims one side of the channel:
+//!
+//! ```no_run
+//! use async_spsc::shm::Shm;
+//! use core::ffi::CStr;
+//!
+//! let name = CStr::from_bytes_with_nul(b"/ingest\0").unwrap();
+//! // In the first process...
+//! let mut sender = Shm::<u64>::create(name, 64).unwrap().sender().unwrap();
+//! sender.send(42).wait().unwrap();
+//! // ...and in the second.
+//! let mut receiver = unsafe { Shm::<u64>::open(name) }.unwrap().receiver().unwrap();
+//! assert_eq!(receiver.receive().wait(), Ok(42));
+//! ```
+//!
+//! Only `Copy` types may be sent: the message is just bytes in a
+//! region we don't control the lifetime of, so we can't run
+//! destructors and it must not contain pointers into either process.
+//!
+//! The creator is responsible for calling [`Shm::unlink`] once the
+//! other process has opened the region (or it is no longer required),
+//! otherwise the name will persist until reboot.
+//!
+//! ## Wakeups
+//!
+//! `AtomicWaker` holds pointers into the process that registered
+//! it, so it can't be shared. Instead, `wait()` sleeps on a futex on
+//! the half of the state word the other side updates, and we only
+//! make the wake syscall when the peer could actually be asleep (the
+//! ring was empty or full before our update).
+//!
+//! The peer can't reach a waker in our process either, so the first
+//! time a future on one side would block, we start a thread to sleep
+//! on that futex on its behalf and wake it. The thread lives as long
+//! as the side does.
+//!
+//! ## Crash detection
+//!
+//! Each side records its PID and a heartbeat in the header when it
+//! claims the channel, and refreshes the heartbeat at most every
+//! [`TICK`] milliseconds as it sends or receives and while it waits. While
+//! waiting (blocking or polling), it also checks on the peer every
+//! tick. If the peer's process has gone, we close
+//! the channel on its behalf: a surviving Receiver may then drain
+//! what was buffered before seeing [`Closed`], and a surviving
+//! Sender may take back what was never received with
+//! [`Sender::reclaim`].
+//!
+//! A peer that dies before claiming its side can't be detected, a
+//! process that has exited but not yet been reaped by its parent is
+//! still considered alive, and both processes must share a PID
+//! namespace. To also catch a peer that is alive but stuck, see
+//! [`Shm::heartbeat_timeout`].
+#[cfg(not(target_os="linux"))]
+compile_error!("the shm feature is only supported on Linux");
+// A critical section only keeps out this process, not the peer.
+#[cfg(feature="critical-section")]
+compile_error!("the shm feature cannot be used with critical-section");
+
+use crate::*;
+use core::ffi::CStr;
+use core::fmt;
+use core::mem::{align_of, forget, size_of};
+use core::ptr::null_mut;
+use crate::atomic::{AtomicI32, AtomicU32};
+use core::time::Duration;
+use libc::c_int;
+#[cfg(feature="async")]
+use std::boxed::Box;
+
+const MAGIC:   u32 = u32::from_be_bytes(*b"SPSC");
+const VERSION: u32 = 2;
+
+/// How often (in milliseconds) a waiting side checks on its peer.
+pub const TICK: u32 = 100;
+
+// Indices into the per-side header fields
+const SENDER:   usize = 0;
+const RECEIVER: usize = 1;
+
+/// The start of a shared region. Everything that is not atomic is
+/// written before `magic` is published and never changes after.
+#[repr(C)]
+pub(crate) struct Header {
+    magic:    AtomicU32,
+    version:  u32,
+    /// The width of the state word, in bytes. Everything after
+    /// this depends on it.
+    width:    u32,
+    size:     u32,
+    align:    u32,
+    capacity: Half,
+    /// The PID of the process that claimed each side, or 0.
+    pids:     [AtomicI32; 2],
+    /// When each side last checked in, in milliseconds on the
+    /// (system-wide) monotonic clock.
+    beats:    [AtomicU32; 2],
+    state:    AtomicWord,
+}
+
+/// Errors that can occur setting up a shm channel.
+#[derive(Debug,Eq,Hash,PartialEq)]
+pub enum ShmError {
+    /// The operating system returned this errno.
+    Os(i32),
+    /// The region is not an spsc channel, or has not finished being
+    /// created.
+    Magic,
+    /// The region was created by an incompatible version of this library.
+    Version(u32),
+    /// The region was created for a different message type or platform.
+    Layout,
+    /// The requested side of the channel has already been claimed.
+    Claimed,
+}
+
+impl ShmError {
+    pub(crate) fn last() -> Self { ShmError::Os(errno()) }
+}
+
+fn errno() -> i32 { unsafe { *libc::__errno_location() } }
+
+/// The time in milliseconds on the monotonic clock, which is shared
+/// between processes. Wraps every 49 days, so compare with care.
+///
+/// The coarse clock is good to a few milliseconds, which is plenty for
+/// heartbeats, and cheap enough to read on every send and receive.
+fn now() -> u32 {
+    let mut ts: libc::timespec = unsafe { core::mem::zeroed() };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_COARSE, &mut ts) };
+    (ts.tv_sec as u64 * 1000 + ts.tv_nsec as u64 / 1_000_000) as u32
+}
+
+/// A process's own mapping of a shared region.
+pub(crate) struct Mapping<T> {
+    header:   NonNull<Header>,
+    len:      usize,
+    /// Waits for the peer on behalf of our side's futures, once the
+    /// mapping has been claimed as one.
+    #[cfg(feature="async")]
+    watcher:  Option<NonNull<Watcher>>,
+    /// How long (in milliseconds) the peer may go without a
+    /// heartbeat before we consider it dead. 0 means forever.
+    timeout:  u32,
+    _phantom: PhantomData<*const T>,
+}
+
+/// Sleeps on the futex for a side's futures, so that the peer's wake
+/// reaches them.
+#[cfg(feature="async")]
+struct Watcher {
+    side:   usize,
+    watch:  std::sync::Mutex<Watch>,
+    wake:   std::sync::Condvar,
+    thread: std::sync::Mutex<Option<std::thread::JoinHandle<()>>>,
+}
+
+#[cfg(feature="async")]
+#[derive(Default)]
+struct Watch {
+    /// The waker to wake when the futex word is no longer `seen`.
+    waker: Option<Waker>,
+    seen:  u32,
+    stop:  bool,
+}
+
+/// What the watcher thread needs, which we promise not to unmap or
+/// free until it has been joined.
+#[cfg(feature="async")]
+struct Watching(Mapping<()>, NonNull<Watcher>);
+
+#[cfg(feature="async")]
+unsafe impl Send for Watching {}
+
+impl<T> Clone for Mapping<T> {
+    fn clone(&self) -> Self { *self }
+}
+
+impl<T> Copy for Mapping<T> {}
+
+impl<T> fmt::Debug for Mapping<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Mapping[{}]", self.len)
+    }
+}
+
+/// The offset of the data array from the start of the region.
+fn data_offset<T>() -> usize {
+    let align = align_of::<T>();
+    size_of::<Header>().div_ceil(align) * align
+}
+
+fn region_len<T>(capacity: Half) -> usize {
+    data_offset::<T>() + size_of::<T>() * capacity as usize
+}
+
+impl<T> Mapping<T> {
+    /// Maps `len` bytes of the region `fd` refers to.
+    unsafe fn map(fd: c_int, len: usize) -> Result<Self, ShmError> {
+        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        let ptr = libc::mmap(null_mut(), len, prot, libc::MAP_SHARED, fd, 0);
+        if ptr == libc::MAP_FAILED { return Err(ShmError::last()); }
+        let header = NonNull::new_unchecked(ptr.cast());
+        Ok(Mapping {
+            header, len, timeout: 0,
+            #[cfg(feature="async")]
+            watcher: None,
+            _phantom: PhantomData
+        })
+    }
+
+    #[inline(always)]
+    fn header(&self) -> &Header { unsafe { self.header.as_ref() } }
+
+    #[inline(always)]
+    pub(crate) fn state(&self) -> &AtomicWord { &self.header().state }
+
+    #[inline(always)]
+    pub(crate) fn data(&self) -> *mut MaybeUninit<T> {
+        unsafe { self.header.as_ptr().cast::<u8>().add(data_offset::<T>()).cast() }
+    }
+
+    #[inline(always)]
+    pub(crate) fn wake_sender(&self, prev: State, cap: Half) {
+        // The Sender only sleeps when it finds the ring full.
+        if prev.is_full(cap) { futex_wake(futex_word(self.state(), true)); }
+    }
+
+    #[inline(always)]
+    pub(crate) fn wake_receiver(&self, prev: State) {
+        // The Receiver only sleeps when it finds the ring empty.
+        if prev.is_empty() { futex_wake(futex_word(self.state(), false)); }
+    }
+
+    /// Flips the bits of `mask` in the Sender's half of the state,
+    /// returning the state from before.
+    #[inline(always)]
+    pub(crate) fn update_sender(&self, mask: Word) -> State { self.update(SENDER, mask) }
+
+    /// Flips the bits of `mask` in the Receiver's half of the state,
+    /// returning the state from before.
+    #[inline(always)]
+    pub(crate) fn update_receiver(&self, mask: Word) -> State { self.update(RECEIVER, mask) }
+
+    /// Close flags are only ever set: the peer may already have set
+    /// ours if it thought us dead.
+    #[inline(always)]
+    fn update(&self, side: usize, mask: Word) -> State {
+        self.beat(side);
+        let close = mask & ANY_CLOSE;
+        let flip = mask & !ANY_CLOSE;
+        if close == 0 { return State(self.state().fetch_xor(flip, Ordering::AcqRel)); }
+        let update = |s: Word| Some((s ^ flip) | close);
+        State(self.state().fetch_update(Ordering::AcqRel, Ordering::Acquire, update).unwrap())
+    }
+
+    /// Refreshes `side`'s heartbeat if it has been at least a tick
+    /// since we last did, so that it stays alive while busy.
+    #[inline(always)]
+    fn beat(&self, side: usize) {
+        let beat = &self.header().beats[side];
+        let now = now();
+        if now.wrapping_sub(beat.load(Ordering::Relaxed)) >= TICK { beat.store(now, Ordering::Release); }
+    }
+
+    /// The Sender waits for the Receiver to change the back.
+    #[inline(always)]
+    pub(crate) fn wait_sender(&self, seen: State) {
+        self.check_in(SENDER);
+        futex_wait(futex_word(self.state(), true), futex_value(seen, true));
+    }
+
+    /// The Receiver waits for the Sender to change the front.
+    #[inline(always)]
+    pub(crate) fn wait_receiver(&self, seen: State) {
+        self.check_in(RECEIVER);
+        futex_wait(futex_word(self.state(), false), futex_value(seen, false));
+    }
+
+    /// Has the watcher wake `waker` once the peer changes its half of
+    /// the state from what it is now.
+    #[cfg(feature="async")]
+    pub(crate) fn register(&self, waker: &Waker) {
+        let Some(watcher) = self.watcher else { return waker.wake_by_ref() };
+        let watcher = unsafe { watcher.as_ref() };
+        self.check_in(watcher.side);
+        let word = futex_word(self.state(), watcher.side == SENDER);
+        let old = {
+            let mut watch = lock(&watcher.watch);
+            watch.seen = unsafe { &*word.cast::<AtomicU32>() }.load(Ordering::Acquire);
+            watch.waker.replace(waker.clone())
+        };
+        drop(old);
+        let mut thread = lock(&watcher.thread);
+        if thread.is_none() {
+            // It has no need of the message type.
+            let map = Mapping {
+                header: self.header, len: self.len, timeout: self.timeout,
+                watcher: self.watcher, _phantom: PhantomData
+            };
+            let watching = Watching(map, NonNull::from(watcher));
+            let spawned = std::thread::Builder::new()
+                .name("spsc-shm".into())
+                .spawn(move || watching.run());
+            match spawned {
+                Ok(handle) => *thread = Some(handle),
+                // The best we can do is ask to be polled again.
+                Err(_) => return waker.wake_by_ref(),
+            }
+        }
+        watcher.wake.notify_one();
+    }
+
+    #[cfg(feature="async")]
+    pub(crate) fn unregister(&self) {
+        if let Some(watcher) = self.watcher {
+            let old = lock(&unsafe { watcher.as_ref() }.watch).waker.take();
+            drop(old);
+        }
+    }
+
+    /// Prepares to wait for the peer's changes on behalf of `side`.
+    #[cfg(feature="async")]
+    fn claimed(self, side: usize) -> Self {
+        let watcher = Watcher {
+            side,
+            watch:  Default::default(),
+            wake:   Default::default(),
+            thread: Default::default(),
+        };
+        Mapping { watcher: Some(NonNull::from(Box::leak(Box::new(watcher)))), ..self }
+    }
+
+    #[cfg(not(feature="async"))]
+    fn claimed(self, _side: usize) -> Self { self }
+
+    /// Claims `side` for the current process.
+    fn claim(&self, side: usize) -> Result<(), ShmError> {
+        let header = self.header();
+        let pid = unsafe { libc::getpid() };
+        header.pids[side].compare_exchange(0, pid, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| ShmError::Claimed)?;
+        header.beats[side].store(now(), Ordering::Release);
+        Ok(())
+    }
+
+    /// If it has been at least a tick since we last did, refreshes
+    /// our heartbeat and closes the peer's side if it has died.
+    fn check_in(&self, side: usize) {
+        let header = self.header();
+        let now = now();
+        if now.wrapping_sub(header.beats[side].load(Ordering::Relaxed)) < TICK { return; }
+        header.beats[side].store(now, Ordering::Release);
+        let peer = side ^ 1;
+        if !self.is_alive(peer, now) {
+            // Not xor: they may have closed normally after all. This
+            // changes the word we wait on, so we notice straight away.
+            let close = if peer == SENDER { S_CLOSE } else { R_CLOSE };
+            self.state().fetch_or(close, Ordering::AcqRel);
+        }
+    }
+
+    fn is_alive(&self, side: usize, now: u32) -> bool {
+        let header = self.header();
+        let pid = header.pids[side].load(Ordering::Acquire);
+        // They haven't turned up yet.
+        if pid == 0 { return true; }
+        if unsafe { libc::kill(pid, 0) } < 0 && errno() == libc::ESRCH { return false; }
+        self.timeout == 0 ||
+            now.wrapping_sub(header.beats[side].load(Ordering::Acquire)) < self.timeout
+    }
+
+    /// ## Safety
+    ///
+    /// The mapping must not be used again.
+    pub(crate) unsafe fn unmap(self) {
+        #[cfg(feature="async")]
+        if let Some(watcher) = self.watcher {
+            let thread = {
+                let watcher = watcher.as_ref();
+                lock(&watcher.watch).stop = true;
+                watcher.wake.notify_one();
+                lock(&watcher.thread).take()
+            };
+            if let Some(thread) = thread {
+                // It may be asleep on the futex, alongside the peer.
+                futex_wake(futex_word(self.state(), watcher.as_ref().side == SENDER));
+                let _ = thread.join();
+            }
+            drop(Box::from_raw(watcher.as_ptr()));
+        }
+        libc::munmap(self.header.as_ptr().cast(), self.len);
+    }
+}
+
+#[cfg(feature="async")]
+impl Watching {
+    fn run(self) {
+        let Watching(map, watcher) = self;
+        let watcher = unsafe { watcher.as_ref() };
+        let word = futex_word(map.state(), watcher.side == SENDER);
+        let mut watch = lock(&watcher.watch);
+        loop {
+            if watch.stop { return; }
+            if watch.waker.is_none() {
+                watch = watcher.wake.wait(watch).unwrap_or_else(|e| e.into_inner());
+                continue;
+            }
+            let seen = watch.seen;
+            drop(watch);
+            futex_wait(word, seen);
+            map.check_in(watcher.side);
+            watch = lock(&watcher.watch);
+            if unsafe { &*word.cast::<AtomicU32>() }.load(Ordering::Acquire) != seen {
+                if let Some(waker) = watch.waker.take() { waker.wake(); }
+            }
+        }
+    }
+}
+
+#[cfg(feature="async")]
+fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// A futex is a 32-bit word. With a 64-bit state word, we wait on
+// whichever half of it the other side updates. Otherwise, the whole
+// state word is a suitable futex and we are the only other writer.
+
+fn futex_word(state: &AtomicWord, back: bool) -> *const u32 {
+    let base = (state as *const AtomicWord).cast::<u32>();
+    if size_of::<Word>() == 4 { return base; }
+    // The back is the high half of the word.
+    let high = back == cfg!(target_endian="little");
+    unsafe { base.add(high as usize) }
+}
+
+// The casts are not needed for every width.
+#[allow(clippy::unnecessary_cast)]
+fn futex_value(seen: State, back: bool) -> u32 {
+    if size_of::<Word>() == 4 { return seen.0 as u32; }
+    (if back { seen.back().0 } else { seen.front().0 }) as u32
+}
+
+/// Sleeps for at most a tick, so that we can check on the peer.
+fn futex_wait(word: *const u32, expected: u32) {
+    let timeout = libc::timespec { tv_sec: 0, tv_nsec: TICK as libc::c_long * 1_000_000 };
+    // Spurious returns (EAGAIN, EINTR, ETIMEDOUT) are fine, the
+    // caller rechecks.
+    unsafe {
+        libc::syscall(libc::SYS_futex, word, libc::FUTEX_WAIT, expected, &timeout as *const libc::timespec);
+    }
+}
+
+// Wakes everything asleep on the word: a side may have a watcher
+// asleep on it as well as a thread in `wait()`.
+fn futex_wake(word: *const u32) {
+    unsafe { libc::syscall(libc::SYS_futex, word, libc::FUTEX_WAKE, c_int::MAX); }
+}
+
+/// A mapping of a shared region that has not yet been claimed as
+/// one side of the channel.
+pub struct Shm<T> {
+    map: Mapping<T>,
+}
+
+impl<T: Copy> Shm<T> {
+    /// Creates a new named region with capacity for `capacity`
+    /// messages. Fails if the name already exists.
+    ///
+    /// Note: will panic if capacity is 0 or too large to represent.
+    pub fn create(name: &CStr, capacity: Half) -> Result<Self, ShmError> {
+        let flags = libc::O_RDWR | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC;
+        let fd = unsafe { libc::shm_open(name.as_ptr(), flags, 0o600) };
+        if fd < 0 { return Err(ShmError::last()); }
+        let ret = unsafe { Self::init(fd, capacity) };
+        unsafe { libc::close(fd) };
+        if ret.is_err() { unsafe { libc::shm_unlink(name.as_ptr()) }; }
+        ret
+    }
+
+    /// Creates a new anonymous region with capacity for `capacity`
+    /// messages, returning it along with a file descriptor for it.
+    ///
+    /// The descriptor belongs to the caller, who may pass it to
+    /// another process (e.g. by inheritance or over a unix socket)
+    /// to be opened with [`Shm::from_fd`].
+    ///
+    /// Note: will panic if capacity is 0 or too large to represent.
+    pub fn memfd(name: &CStr, capacity: Half) -> Result<(Self, c_int), ShmError> {
+        let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 { return Err(ShmError::last()); }
+        match unsafe { Self::init(fd, capacity) } {
+            Ok(shm) => Ok((shm, fd)),
+            Err(e) => {
+                unsafe { libc::close(fd) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Opens an existing named region.
+    ///
+    /// ## Safety
+    ///
+    /// The region must have been created for this `T`. We can only
+    /// check that the size and alignment match.
+    pub unsafe fn open(name: &CStr) -> Result<Self, ShmError> {
+        let fd = libc::shm_open(name.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC, 0);
+        if fd < 0 { return Err(ShmError::last()); }
+        let ret = Self::from_fd(fd);
+        libc::close(fd);
+        ret
+    }
+
+    /// Opens an existing region from a file descriptor. The
+    /// descriptor is not consumed and may be closed afterwards.
+    ///
+    /// ## Safety
+    ///
+    /// The region must have been created for this `T`. We can only
+    /// check that the size and alignment match.
+    pub unsafe fn from_fd(fd: c_int) -> Result<Self, ShmError> {
+        let mut stat: libc::stat = core::mem::zeroed();
+        if libc::fstat(fd, &mut stat) < 0 { return Err(ShmError::last()); }
+        let len = stat.st_size as usize;
+        if len < size_of::<Header>() { return Err(ShmError::Magic); }
+        let map = Mapping::map(fd, len)?;
+        let shm = Shm { map };
+        shm.validate()?;
+        Ok(shm)
+    }
+
+    /// Removes a name created with [`Shm::create`]. Channels already
+    /// opened are unaffected.
+    pub fn unlink(name: &CStr) -> Result<(), ShmError> {
+        if unsafe { libc::shm_unlink(name.as_ptr()) } < 0 {
+            return Err(ShmError::last());
+        }
+        Ok(())
+    }
+
+    /// Sizes and initialises a fresh region.
+    pub(crate) unsafe fn init(fd: c_int, capacity: Half) -> Result<Self, ShmError> {
+        assert!(capacity > 0);
+        assert!(capacity <= MAX_CAPACITY);
+        let len = region_len::<T>(capacity);
+        if libc::ftruncate(fd, len as libc::off_t) < 0 { return Err(ShmError::last()); }
+        let map = Mapping::map(fd, len)?;
+        // A fresh region is zeroed, so we only fill in the constants.
+        let header = map.header.as_ptr();
+        (*header).version  = VERSION;
+        (*header).width    = size_of::<Word>() as u32;
+        (*header).size     = size_of::<T>() as u32;
+        (*header).align    = align_of::<T>() as u32;
+        (*header).capacity = capacity;
+        (*header).magic.store(MAGIC, Ordering::Release);
+        Ok(Shm { map })
+    }
+
+    fn validate(&self) -> Result<(), ShmError> {
+        let header = self.map.header();
+        if header.magic.load(Ordering::Acquire) != MAGIC { return Err(ShmError::Magic); }
+        if header.version != VERSION { return Err(ShmError::Version(header.version)); }
+        if header.width != size_of::<Word>() as u32
+            || header.size != size_of::<T>() as u32
+            || header.align != align_of::<T>() as u32
+            || header.capacity == 0
+            || header.capacity > MAX_CAPACITY
+            || self.map.len < region_len::<T>(header.capacity) {
+            return Err(ShmError::Layout);
+        }
+        // A region that outlived its creator (e.g. a file) may have
+        // been damaged, and we trust the positions for indexing.
+        let cap = header.capacity;
+        let state = State(header.state.load(Ordering::Acquire));
+        if state.front().position() >= 2 * cap
+            || state.back().position() >= 2 * cap
+            || state.len(cap) > cap {
+            return Err(ShmError::Layout);
+        }
+        Ok(())
+    }
+
+    /// Forgets about the previous users of the region, reopening both
+    /// sides of the channel without disturbing the messages in it.
+    ///
+    /// ## Safety
+    ///
+    /// Neither side may still be in use.
+    #[cfg(feature="file")]
+    pub(crate) unsafe fn reset(&self) {
+        let header = self.map.header();
+        for side in [SENDER, RECEIVER] {
+            header.pids[side].store(0, Ordering::Relaxed);
+            header.beats[side].store(0, Ordering::Relaxed);
+        }
+        header.state.fetch_and(!ANY_CLOSE, Ordering::AcqRel);
+    }
+
+    /// The maximum number of messages the channel can hold.
+    pub fn capacity(&self) -> Half { self.map.header().capacity }
+
+    /// Also considers the peer dead if it has not checked in for
+    /// longer than `timeout`.
+    ///
+    /// Note: a side only checks in when it claims the channel or
+    /// while it is waiting, so a peer that is merely busy (or idle
+    /// without waiting) for longer than this will be disconnected.
+    /// Rounded to milliseconds.
+    pub fn heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.map.timeout = timeout.as_millis().clamp(1, u32::MAX as u128 / 2) as u32;
+        self
+    }
+
+    /// Claims the sending side of the channel.
+    pub fn sender(self) -> Result<Sender<'static, 'static, T>, ShmError> {
+        self.map.claim(SENDER)?;
+        let (map, cap) = (self.map.claimed(SENDER), self.capacity());
+        forget(self);
+        let state = State(map.state().load(Ordering::Acquire));
+        Ok(Sender::new(Holder::Shm(map), state, cap))
+    }
+
+    /// Claims the receiving side of the channel.
+    pub fn receiver(self) -> Result<Receiver<'static, 'static, T>, ShmError> {
+        self.map.claim(RECEIVER)?;
+        let (map, cap) = (self.map.claimed(RECEIVER), self.capacity());
+        forget(self);
+        let state = State(map.state().load(Ordering::Acquire));
+        Ok(Receiver::new(Holder::Shm(map), state, cap))
+    }
+}
+
+impl<T> fmt::Debug for Shm<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Shm[{}]", self.map.header().capacity)
+    }
+}
+
+impl<T> Drop for Shm<T> {
+    fn drop(&mut self) { unsafe { self.map.unmap() } }
+}
+
+unsafe impl<T: Send> Send for Shm<T> {}
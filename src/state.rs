@@ -1,19 +1,39 @@
 use core::convert::TryInto;
-
-#[cfg(target_pointer_width="64")] pub type Half = u32;
-#[cfg(target_pointer_width="32")] pub type Half = u16;
-#[cfg(target_pointer_width="16")] pub type Half = u8;
-
-#[cfg(target_pointer_width="64")] pub const BITS: usize = 32;
-#[cfg(target_pointer_width="32")] pub const BITS: usize = 16;
-#[cfg(target_pointer_width="16")] pub const BITS: usize = 8;
+use crate::atomic::{AtomicU16, AtomicU32, Ordering};
+#[cfg(any(target_pointer_width="64", all(feature="wide", target_pointer_width="32", target_has_atomic="64")))]
+use crate::atomic::AtomicU64;
+
+// The state word is pointer-sized, unless the `wide` feature asks for
+// a 64-bit one on a 32-bit target which has 64-bit atomics.
+#[cfg(any(target_pointer_width="64", all(feature="wide", target_pointer_width="32", target_has_atomic="64")))]
+mod width {
+    pub type Half = u32;
+    pub type Word = u64;
+    pub type AtomicWord = crate::atomic::AtomicU64;
+    pub const BITS: usize = 32;
+}
+#[cfg(all(target_pointer_width="32", not(all(feature="wide", target_has_atomic="64"))))]
+mod width {
+    pub type Half = u16;
+    pub type Word = u32;
+    pub type AtomicWord = crate::atomic::AtomicU32;
+    pub const BITS: usize = 16;
+}
+#[cfg(target_pointer_width="16")]
+mod width {
+    pub type Half = u8;
+    pub type Word = u16;
+    pub type AtomicWord = crate::atomic::AtomicU16;
+    pub const BITS: usize = 8;
+}
+pub use width::*;
 
 pub const HIGH_BIT:     Half = 1 << (BITS - 1);
-pub const S_CLOSE:      usize = HIGH_BIT as usize;
-pub const R_CLOSE:      usize = S_CLOSE << BITS;
-pub const ANY_CLOSE:    usize = S_CLOSE | R_CLOSE;
-pub const FRONT:        usize = Half::MAX as usize;
-pub const BACK:         usize = !FRONT;
+pub const S_CLOSE:      Word = HIGH_BIT as Word;
+pub const R_CLOSE:      Word = S_CLOSE << BITS;
+pub const ANY_CLOSE:    Word = S_CLOSE | R_CLOSE;
+pub const FRONT:        Word = Half::MAX as Word;
+pub const BACK:         Word = !FRONT;
 pub const MAX_CAPACITY: Half  = (HIGH_BIT >> 1) - 1;
 
 #[derive(Copy,Clone,Debug,Eq,PartialEq)]
@@ -24,6 +44,10 @@ impl HalfState {
     #[inline(always)]
     pub fn position(self) -> Half { self.0 & !(1 << (BITS - 1)) }
 
+    /// The slot this position refers to. This is a division unless the
+    /// compiler can see the capacity is a power of two (see
+    /// [`Pow2`](crate::Pow2)), when it is a mask.
+    #[inline(always)]
     pub fn index(self, capacity: Half) -> usize { (self.0 % capacity) as usize }
 
     #[inline(always)]
@@ -59,7 +83,7 @@ impl HalfState {
 ///    little space as possible so we can pack them into a single
 ///    atomic. This is the approach we chose.
 #[derive(Copy,Clone,Debug,Eq,PartialEq)]
-pub struct State(pub(crate) usize);
+pub struct State(pub(crate) Word);
 
 impl State {
     #[inline(always)]
@@ -74,12 +98,12 @@ impl State {
 
     #[inline(always)]
     pub fn with_front(self, front: HalfState) -> State {
-        State((self.0 & BACK) | front.0 as usize)
+        State((self.0 & BACK) | front.0 as Word)
     }
 
     #[inline(always)]
     pub fn with_back(self, back: HalfState) -> State {
-        State((self.0 & FRONT) | ((back.0 as usize) << BITS) )
+        State((self.0 & FRONT) | ((back.0 as Word) << BITS) )
     }
 
     #[inline(always)]
@@ -94,8 +118,8 @@ impl State {
     /// The number of slots available for writing.
     #[inline(always)]
     pub fn space(self, cap: Half) -> Half { cap - self.len(cap)}
-    /// The number of slots available for reading.
 
+    /// The number of slots available for reading.
     #[inline(always)]
     pub fn len(self, cap: Half) -> Half {
         let f = self.front().position();
@@ -107,3 +131,87 @@ impl State {
         }
     }
 }
+
+/// An atomic a channel's state may be packed into. A narrower one than
+/// [`AtomicWord`] has narrower halves, with the close flags moved down
+/// to match; states are converted as they are loaded and stored, so
+/// everything else works with our usual layout.
+///
+/// Implemented for the atomics twice as wide as each supported index
+/// type (see [`Capacity`](crate::Capacity)).
+pub trait AtomicState: Default + Send + Sync + 'static {
+    /// The largest capacity a state of this width can describe.
+    const MAX_CAPACITY: Half;
+    /// The largest count it can keep of zero-sized messages, which need
+    /// no slots, so their positions may wrap at the most it can hold.
+    const MAX_COUNT: Half = 2 * Self::MAX_CAPACITY + 1;
+    fn load_state(&self, order: Ordering) -> State;
+    /// Flips the bits of `mask`, returning the state from before.
+    fn xor_state(&self, mask: Word, order: Ordering) -> State;
+    /// Replaces the state with `next` if it is still `seen`, returning
+    /// the state from before either way.
+    fn swap_state(&self, seen: State, next: State, order: Ordering) -> Result<State, State>;
+    /// Replaces the state with `f` of it, with relaxed ordering.
+    fn update_state(&self, f: impl Fn(State) -> State);
+}
+
+/// Moves the close flags of a state packed with `bits` wide halves to
+/// where ours go.
+#[inline(always)]
+fn widen(n: Word, bits: usize) -> State {
+    if bits == BITS { return State(n); }
+    let half = |h: Word| (h & ((1 << (bits - 1)) - 1)) | (((h >> (bits - 1)) & 1) << (BITS - 1));
+    State(half(n & ((1 << bits) - 1)) | (half(n >> bits) << BITS))
+}
+
+/// The reverse of `widen`.
+#[inline(always)]
+fn narrow(state: State, bits: usize) -> Word {
+    if bits == BITS { return state.0; }
+    let half = |h: Word| (h & ((1 << (bits - 1)) - 1)) | (((h >> (BITS - 1)) & 1) << (bits - 1));
+    half(state.0 & FRONT) | (half(state.0 >> BITS) << bits)
+}
+
+macro_rules! atomic_state {
+    ($atomic:ty, $int:ty) => {
+        // The casts are not needed for the widest.
+        #[allow(clippy::unnecessary_cast)]
+        impl AtomicState for $atomic {
+            const MAX_CAPACITY: Half = ((1 << (<$int>::BITS / 2 - 2)) - 1) as Half;
+
+            #[inline(always)]
+            fn load_state(&self, order: Ordering) -> State {
+                widen(self.load(order) as Word, <$int>::BITS as usize / 2)
+            }
+
+            #[inline(always)]
+            fn xor_state(&self, mask: Word, order: Ordering) -> State {
+                let bits = <$int>::BITS as usize / 2;
+                widen(self.fetch_xor(narrow(State(mask), bits) as $int, order) as Word, bits)
+            }
+
+            #[inline(always)]
+            fn swap_state(&self, seen: State, next: State, order: Ordering) -> Result<State, State> {
+                let bits = <$int>::BITS as usize / 2;
+                let (seen, next) = (narrow(seen, bits) as $int, narrow(next, bits) as $int);
+                self.compare_exchange(seen, next, order, Ordering::Acquire)
+                    .map(|n| widen(n as Word, bits))
+                    .map_err(|n| widen(n as Word, bits))
+            }
+
+            #[inline(always)]
+            fn update_state(&self, f: impl Fn(State) -> State) {
+                let bits = <$int>::BITS as usize / 2;
+                let _ = self.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                    Some(narrow(f(widen(n as Word, bits)), bits) as $int)
+                });
+            }
+        }
+    }
+}
+
+atomic_state!(AtomicU16, u16);
+#[cfg(not(target_pointer_width="16"))]
+atomic_state!(AtomicU32, u32);
+#[cfg(any(target_pointer_width="64", all(feature="wide", target_pointer_width="32", target_has_atomic="64")))]
+atomic_state!(AtomicU64, u64);
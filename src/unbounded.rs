@@ -0,0 +1,199 @@
+//! Channels which never fill up.
+//!
+//! Some producers cannot tolerate backpressure. Channels from
+//! [`unbounded_spsc`] are a chain of rings
+//! ("segments") laid out like any other channel's page. When the
+//! Sender fills one, it links a new one on and seals the old by closing
+//! its side of it, so sends never fail for lack of space. The Receiver
+//! drains each segment as usual, and on finding one sealed and empty,
+//! leaves it for the next. Whichever side leaves a segment last frees
+//! it.
+//!
+//! ```
+//! use async_spsc::unbounded_spsc;
+//!
+//! let (mut sender, mut receiver) = unbounded_spsc::<i32>(4);
+//! for i in 0..100 { sender.send(i).now().unwrap(); }
+//! for i in 0..100 { assert_eq!(Ok(Some(i)), receiver.receive().now()); }
+//! ```
+//!
+//! Everything else works per segment: [`Sender::capacity`] is the size
+//! of one, [`Sender::wait_space`] waits for space in the current one,
+//! and [`Receiver::wait_len`] may resolve with fewer than it was asked
+//! for when the rest are in the next.
+//!
+//! The same chain lets channels from
+//! [`spsc_resizable`] change size on the fly.
+//! These fill up like any other, but [`Sender::grow`] and
+//! [`Sender::shrink`] move the Sender on to a segment of the new size.
+//! The messages already sent stay where they are, and the Receiver
+//! takes the new size once it has drained them. Until it does, they
+//! count against the new capacity: the new segment starts with as many
+//! slots taken, which the Receiver skips when it gets there. So there
+//! are never more messages in flight than the capacity:
+//!
+//! ```
+//! use async_spsc::spsc_resizable;
+//!
+//! let (mut sender, mut receiver) = spsc_resizable::<i32>(2);
+//! for i in 0..2 { sender.send(i).now().unwrap(); }
+//! assert!(sender.send(2).now().is_err());
+//! sender.grow(4).unwrap();
+//! for i in 2..4 { sender.send(i).now().unwrap(); }
+//! assert!(sender.send(4).now().is_err());
+//! for i in 0..4 { assert_eq!(Ok(Some(i)), receiver.receive().now()); }
+//! ```
+use crate::*;
+#[cfg(feature="alloc")]
+use core::cell::UnsafeCell;
+#[cfg(feature="alloc")]
+use alloc::boxed::Box;
+
+/// The header of a segment of an unbounded channel.
+#[cfg(feature="alloc")]
+#[derive(Debug)]
+pub(crate) struct Segment<T, N = DefaultNotify, A = AtomicWord> {
+    // The notifier lives apart, as the sides may be on different
+    // segments but must reach each other through the same one.
+    pub(crate) atomics: Atomics<(), A>,
+    // Each side's close flag again, flipped only once it is done with
+    // the segment, so that exactly one side frees it.
+    pub(crate) closes:  A,
+    // How many slots this segment has.
+    cap:                Half,
+    // Whether the Sender moves on rather than finding it full.
+    unbounded:          bool,
+    // How many slots it starts out with taken, standing for messages
+    // still in flight in the segments before it when it was linked.
+    carried:            Half,
+    // The segment the Sender moved on to, written before it sealed
+    // this one.
+    next:               UnsafeCell<Option<SegmentRef<T, N, A>>>,
+    // The channel's notifier, freed along with the last segment.
+    notify:             NonNull<N>,
+}
+
+#[cfg(feature="alloc")]
+impl<T, N, A> Segment<T, N, A> {
+    #[inline(always)]
+    pub(crate) fn notify(&self) -> &N { unsafe { self.notify.as_ref() } }
+
+    /// Frees the notifier if this is the last segment of the chain.
+    ///
+    /// Safe only if both sides are done with the segment.
+    pub(crate) unsafe fn free_notify(&self) {
+        if (*self.next.get()).is_none() { drop(Box::from_raw(self.notify.as_ptr())); }
+    }
+}
+
+/// Moves the notifier for a new chain of segments onto the heap.
+#[cfg(feature="alloc")]
+pub(crate) fn alloc_notify<N>(notify: N) -> NonNull<N> {
+    NonNull::from(Box::leak(Box::new(notify)))
+}
+
+#[cfg(feature="alloc")]
+pub(crate) type SegmentRef<T, N, A> = PageRef<Segment<T, N, A>, T>;
+
+/// Allocates a segment of `cap` slots, whose watermarks start out as
+/// `marks` (or as near as it can hold) and whose first `carried` slots
+/// are taken (see `Holder::enter`).
+#[cfg(feature="alloc")]
+pub(crate) fn alloc_segment<T, N: Notify, A: AtomicState>(cap: Half, unbounded: bool, notify: NonNull<N>, marks: State,
+                                                          carried: Half) -> SegmentRef<T, N, A> {
+    let atomics = Atomics { state: A::default(), marks: A::default(), notify: () };
+    atomics.state.update_state(|_| State(0).with_front(HalfState(carried)));
+    // They are stored less one.
+    let (front, back) = (marks.front().0.min(cap - 1), marks.back().0.min(cap - 1));
+    atomics.marks.update_state(|_| marks.with_front(HalfState(front)).with_back(HalfState(back)));
+    let segment = Segment { atomics, closes: A::default(), cap, unbounded, carried, next: UnsafeCell::new(None),
+                             notify };
+    PageRef::new(segment, crate::pages(cap))
+}
+
+impl<'a, 'b, T, N: Notify, A: AtomicState> Holder<'a, 'b, T, N, A> {
+
+    /// Whether this is one of a chain of segments.
+    #[inline(always)]
+    pub(crate) fn is_segment(&self) -> bool {
+        #[cfg(feature="alloc")]
+        return matches!(self, Holder::Segment(_));
+        #[cfg(not(feature="alloc"))]
+        false
+    }
+
+    /// Whether the Sender moves on to a new segment rather than finding
+    /// the ring full.
+    #[cfg(any(feature="alloc", feature="async"))]
+    #[inline(always)]
+    pub(crate) fn is_unbounded(&self) -> bool {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self { return unsafe { p.header() }.unbounded; }
+        false
+    }
+
+    /// How many slots the segment has, which may differ from the last
+    /// if the channel was resized.
+    pub(crate) fn segment_capacity(&self) -> Option<Half> {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self { return Some(unsafe { p.header() }.cap); }
+        None
+    }
+
+    /// The segment the Sender moved on to. Only to be called once we
+    /// have seen it seal this one.
+    #[inline(always)]
+    pub(crate) fn next_segment(&self) -> Option<Self> {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self { return unsafe { *p.header().next.get() }.map(Holder::Segment); }
+        None
+    }
+
+    /// Allocates a new segment like ours, but of `cap` slots of which
+    /// `carried` are taken, and links it on for the Sender to move on to
+    /// once it seals ours.
+    #[cfg(feature="alloc")]
+    pub(crate) fn link(&self, cap: Half, carried: Half) -> Self {
+        let p = match self {
+            Holder::Segment(p) => p,
+            _ => unreachable!("only segments are linked"),
+        };
+        let header = unsafe { p.header() };
+        let marks = header.atomics.marks.load_state(Ordering::Relaxed);
+        let next = alloc_segment(cap, header.unbounded, header.notify, marks, carried);
+        // The Receiver will not look until we seal ours.
+        unsafe { *header.next.get() = Some(next) };
+        Holder::Segment(next)
+    }
+
+    /// Leaves a segment on behalf of the Receiver, returning the next
+    /// if the Sender has sealed it and moved on.
+    ///
+    /// Safe only if we have no further use for the segment.
+    pub(crate) unsafe fn leave(self) -> Option<Self> {
+        // Each may be a different size, if the channel was resized.
+        let cap = self.segment_capacity().unwrap();
+        let prev = self.update(Side::Receiver, R_CLOSE);
+        // The link is settled once it is sealed, and we must read it
+        // before we settle who frees the segment.
+        let next = if prev.front().is_closed() { self.next_segment() } else { None };
+        self.settle(Side::Receiver, prev, R_CLOSE, cap);
+        if let Some(next) = next.as_ref() { next.enter(); }
+        next
+    }
+
+    /// Moves the Receiver past the slots a segment starts out with
+    /// taken, now that it has drained the messages they stood for, and
+    /// lets the Sender know it has the room. Only to be called once,
+    /// as the Receiver reaches the segment.
+    fn enter(&self) {
+        #[cfg(feature="alloc")]
+        if let Holder::Segment(p) = self {
+            let (cap, carried) = { let header = unsafe { p.header() }; (header.cap, header.carried) };
+            if carried == 0 { return; }
+            let mask = (carried as Word) << BITS;
+            let prev = self.update(Side::Receiver, mask);
+            self.wake_sender(prev, State(prev.0 ^ mask), cap);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! How long a side keeps checking before it goes to sleep.
+//!
+//! When a send finds the ring full or a receive finds it empty, the
+//! default is to register a waker (or block, for `wait()`) straight
+//! away. When the peer is pinned to another core and only nanoseconds
+//! behind, the wakeup round trip can dominate latency, so a side may
+//! instead keep checking for a while first:
+//!
+//! ```
+//! use async_spsc::{spsc, WaitStrategy};
+//!
+//! let (mut sender, mut receiver) = spsc::<i32>(64);
+//! sender.set_wait_strategy(WaitStrategy::Spin(1000));
+//! receiver.set_wait_strategy(WaitStrategy::Spin(1000));
+//! ```
+//!
+//! This trades CPU time for latency, so it is best kept for threads
+//! which have a core to themselves.
+
+/// What a side does when it cannot make progress. Set on each side
+/// with `set_wait_strategy`.
+#[derive(Clone,Copy,Debug,Default,Eq,Hash,PartialEq)]
+pub enum WaitStrategy {
+    /// Register a waker or block immediately.
+    #[default]
+    Park,
+    /// Check up to this many more times, busy-spinning in between,
+    /// before parking.
+    Spin(u32),
+    /// Busy-spin as for `Spin`, then yield the thread to the scheduler
+    /// up to `yields` times before parking.
+    #[cfg(feature="std")]
+    SpinYield { spins: u32, yields: u32 },
+}
+
+impl WaitStrategy {
+    /// Repeatedly checks `ready` according to the strategy. Returns
+    /// true if it became ready, false if we should park.
+    #[inline(always)]
+    pub(crate) fn spin(self, ready: impl Fn() -> bool) -> bool {
+        match self {
+            WaitStrategy::Park => false,
+            WaitStrategy::Spin(spins) => spin(spins, &ready),
+            #[cfg(feature="std")]
+            WaitStrategy::SpinYield { spins, yields } => {
+                if spin(spins, &ready) { return true; }
+                for _ in 0..yields {
+                    std::thread::yield_now();
+                    if ready() { return true; }
+                }
+                false
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn spin(spins: u32, ready: &impl Fn() -> bool) -> bool {
+    for _ in 0..spins {
+        core::hint::spin_loop();
+        if ready() { return true; }
+    }
+    false
+}
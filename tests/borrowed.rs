@@ -0,0 +1,54 @@
+use async_spsc::*;
+use core::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+mod common;
+use common::*;
+
+#[test]
+fn ping_pong() {
+    let mut buffer = [MaybeUninit::<i32>::uninit(); 2];
+    let mut spsc = Spsc::new(&mut buffer);
+    let (mut s, mut r) = spsc.split();
+    assert_eq!(2, s.capacity());
+    for i in 0..10 {
+        assert_eq!(Ok(()), s.send(i).now());
+        assert_eq!(Ok(()), s.send(i + 1).now());
+        assert!(s.send(i + 2).now().is_err());
+        assert_eq!(Ok(Some(i)), r.receive().now());
+        assert_eq!(Ok(Some(i + 1)), r.receive().now());
+        assert_eq!(Ok(None), r.receive().now());
+    }
+    drop(s);
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn split_again() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let mut buffer: [MaybeUninit<Drops>; 3] = [const { MaybeUninit::uninit() }; 3];
+    let mut spsc = Spsc::new(&mut buffer);
+    for round in 1..=3 {
+        let (mut s, r) = spsc.split();
+        s.send(Drops(drops.clone())).now().ok().unwrap();
+        s.send(Drops(drops.clone())).now().ok().unwrap();
+        drop(r);
+        drop(s);
+        // Those left in flight are dropped with the last side.
+        assert_eq!(round * 2, drops.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 1_000;
+    let mut buffer = [MaybeUninit::<usize>::uninit(); 4];
+    let mut spsc = Spsc::new(&mut buffer);
+    let (mut s, mut r) = spsc.split();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            for i in 0..COUNT { s.send(i).wait().unwrap(); }
+        });
+        for i in 0..COUNT { assert_eq!(Ok(i), r.receive().wait()); }
+    });
+}
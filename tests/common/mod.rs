@@ -0,0 +1,61 @@
+// Helpers shared between the integration tests. Each test crate only
+// uses some of them.
+#![allow(dead_code)]
+use async_spsc::*;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Wake;
+use std::thread::{self, Thread};
+
+// Counts how many times it has been dropped.
+pub struct Drops(pub Arc<AtomicUsize>);
+
+impl Drop for Drops {
+    fn drop(&mut self) { self.0.fetch_add(1, Ordering::Relaxed); }
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) { self.0.unpark(); }
+}
+
+// Polls until ready, parking the thread until woken in between.
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut ctx = Context::from_waker(&waker);
+    let mut fut = pin!(fut);
+    loop {
+        if let Poll::Ready(value) = fut.as_mut().poll(&mut ctx) { return value; }
+        thread::park();
+    }
+}
+
+// Calls `send` until the channel is not full, yielding in between.
+pub fn send_or_yield<T>(mut send: impl FnMut(T) -> Result<(), SendError<T>>, mut value: T)
+                        -> Result<(), SendError<T>> {
+    loop {
+        match send(value) {
+            Err(SendError { kind: SendErrorKind::Full, value: v }) => {
+                value = v;
+                thread::yield_now();
+            }
+            ret => return ret,
+        }
+    }
+}
+
+// Passes each message `receive` gets to `f`, yielding whenever there
+// are none, until the Sender closes.
+pub fn receive_all<T>(mut receive: impl FnMut() -> Result<Option<T>, Closed>, mut f: impl FnMut(T)) {
+    loop {
+        match receive() {
+            Ok(Some(value)) => f(value),
+            Ok(None) => thread::yield_now(),
+            Err(Closed) => return,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use async_spsc::*;
+use core::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Counts how many times it has been dropped.
+struct Drops(Arc<AtomicUsize>);
+
+impl Drop for Drops {
+    fn drop(&mut self) { self.0.fetch_add(1, Ordering::Relaxed); }
+}
+
+#[test]
+fn widths() {
+    assert_eq!(2, size_of::<<u8 as Capacity>::Atomic>());
+    assert_eq!(4, size_of::<<u16 as Capacity>::Atomic>());
+}
+
+#[test]
+fn wrap_around() {
+    for cap in [1u8, 2, 5, 8, 63] {
+        let (mut s, mut r) = spsc_compact::<usize, u8>(cap);
+        assert_eq!(cap as usize, s.capacity() as usize);
+        let mut next = 0;
+        for i in 0..cap as usize * 5 {
+            s.send(i).now().unwrap();
+            if i % 3 == 2 || s.is_full() {
+                while let Ok(Some(v)) = r.receive().now() {
+                    assert_eq!(next, v);
+                    next += 1;
+                }
+            }
+        }
+        drop(s);
+        while let Ok(Some(v)) = r.receive().now() {
+            assert_eq!(next, v);
+            next += 1;
+        }
+        assert_eq!(cap as usize * 5, next);
+        assert_eq!(Err(Closed), r.receive().now());
+    }
+}
+
+#[test]
+fn u16_capacity() {
+    let (mut s, mut r) = spsc_compact::<u32, u16>(1000);
+    for i in 0..1000 { s.send(i).now().unwrap(); }
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 0 }), s.send(0).now());
+    for i in 0..1000 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+}
+
+#[test]
+#[should_panic]
+fn too_big() { spsc_compact::<i32, u8>(64); }
+
+#[test]
+fn close() {
+    for receiver_first in [false, true] {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut s, mut r) = spsc_compact::<Drops, u8>(4);
+        for _ in 0..3 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        drop(r.receive().now());
+        if receiver_first {
+            drop(r);
+            assert!(s.send(Drops(drops.clone())).now().is_err());
+            drop(s.reclaim());
+            assert_eq!(3, drops.load(Ordering::Relaxed));
+            drop(s);
+        } else {
+            drop(s);
+            drop(r.receive().now());
+            drop(r);
+        }
+        assert_eq!(if receiver_first { 4 } else { 3 }, drops.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn callback_min_batch() {
+    let receivers = Arc::new(AtomicUsize::new(0));
+    let r2 = receivers.clone();
+    let (mut s, mut r) = spsc_compact_with::<i32, _, u8>(8, Callback(move |side| {
+        if side == Side::Receiver { r2.fetch_add(1, Ordering::Relaxed); }
+    }));
+    // The watermarks are packed narrower too.
+    r.set_min_batch(3);
+    for i in 0..2 { s.send(i).now().unwrap(); }
+    assert_eq!(0, receivers.load(Ordering::Relaxed));
+    s.send(2).now().unwrap();
+    assert_eq!(1, receivers.load(Ordering::Relaxed));
+    assert_eq!(Ok(Some(0)), r.receive().now());
+}
+
+#[test]
+fn threads() {
+    let (mut s, mut r) = spsc_compact::<usize, u8>(2);
+    let t = std::thread::spawn(move || {
+        for i in 0..100_000 {
+            let mut value = i;
+            while let Err(e) = s.send(value).now() {
+                value = e.value;
+                std::thread::yield_now();
+            }
+        }
+    });
+    let mut next = 0;
+    while next < 100_000 {
+        match r.receive().now() {
+            Ok(Some(v)) => { assert_eq!(next, v); next += 1; }
+            _ => std::thread::yield_now(),
+        }
+    }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().now());
+}
@@ -0,0 +1,140 @@
+#![cfg(feature="fd")]
+use async_spsc::*;
+use mio::{Events, Interest, Poll, Token};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+mod common;
+use common::*;
+
+// Whether the fd is readable right now.
+fn readable(fd: &impl AsRawFd) -> bool {
+    let mut poll = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+    1 == unsafe { libc::poll(&mut poll, 1, 0) }
+}
+
+#[test]
+fn receiver_readiness() {
+    let (mut s, mut r) = spsc_fd::<i32>(2).unwrap();
+    assert!(!readable(&r));
+    assert_eq!(Ok(()), s.send(1).now());
+    assert!(readable(&r));
+    assert_eq!(Ok(()), s.send(2).now());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert!(readable(&r));
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    // We only clear it when we find nothing to receive.
+    assert!(readable(&r));
+    assert_eq!(Ok(None), r.receive().now());
+    assert!(!readable(&r));
+    drop(s);
+    assert!(readable(&r));
+    assert_eq!(Err(Closed), r.receive().now());
+    assert!(readable(&r));
+}
+
+#[test]
+fn sender_readiness() {
+    let (mut s, mut r) = spsc_fd::<i32>(1).unwrap();
+    assert_eq!(Ok(()), s.send(1).now());
+    assert!(!readable(&s));
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 2 }), s.send(2).now());
+    assert!(!readable(&s));
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert!(readable(&s));
+    assert_eq!(Ok(()), s.send(2).now());
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 3 }), s.send(3).now());
+    assert!(!readable(&s));
+    drop(r);
+    assert!(readable(&s));
+}
+
+#[test]
+fn mio_poll() {
+    let (mut s, mut r) = spsc_fd::<i32>(4).unwrap();
+    let mut poll = Poll::new().unwrap();
+    let mut events = Events::with_capacity(4);
+    poll.registry().register(&mut r, Token(7), Interest::READABLE).unwrap();
+    poll.poll(&mut events, Some(Duration::from_millis(10))).unwrap();
+    assert!(events.is_empty());
+    let t = std::thread::spawn(move || {
+        for i in 0..1000 {
+            let mut value = i;
+            loop {
+                match s.send(value).now() {
+                    Ok(()) => break,
+                    Err(e) => { value = e.value; std::thread::yield_now(); }
+                }
+            }
+        }
+    });
+    let mut next = 0;
+    while next < 1000 {
+        poll.poll(&mut events, None).unwrap();
+        for event in events.iter() {
+            assert_eq!(Token(7), event.token());
+            while let Ok(Some(value)) = r.receive().now() {
+                assert_eq!(next, value);
+                next += 1;
+            }
+        }
+    }
+    t.join().unwrap();
+    poll.registry().deregister(&mut r).unwrap();
+}
+
+#[test]
+fn wait() {
+    let (mut s, mut r) = spsc_fd::<usize>(1).unwrap();
+    let t = std::thread::spawn(move || {
+        for i in 0..10000 { s.send(i).wait().unwrap(); }
+    });
+    for i in 0..10000 { assert_eq!(Ok(i), r.receive().wait()); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().wait());
+}
+
+#[cfg(feature="async")]
+#[test]
+fn await_receive() {
+    let (mut s, mut r) = spsc_fd::<usize>(1).unwrap();
+    let t = std::thread::spawn(move || {
+        for i in 0..1000 { block_on(s.send(i)).unwrap(); }
+    });
+    for i in 0..1000 { assert_eq!(Ok(i), block_on(r.receive())); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), block_on(r.receive()));
+}
+
+#[test]
+fn unbounded_keeps_fd() {
+    let (mut s, mut r) = unbounded_spsc_with::<i32, _>(2, fd::EventFds::new().unwrap());
+    let (sfd, rfd) = (s.as_raw_fd(), r.as_raw_fd());
+    assert!(!readable(&r));
+    for i in 0..10 { assert_eq!(Ok(()), s.send(i).now()); }
+    assert!(readable(&r));
+    for i in 0..10 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    assert_eq!((sfd, rfd), (s.as_raw_fd(), r.as_raw_fd()));
+    assert_eq!(Ok(None), r.receive().now());
+    assert!(!readable(&r));
+    assert_eq!(Ok(()), s.send(10).now());
+    assert!(readable(&r));
+}
+
+#[test]
+fn padded_readiness() {
+    let (mut s, mut r) = spsc_padded_with::<i32, _>(2, fd::EventFds::new().unwrap());
+    assert_eq!(Ok(None), r.receive().now());
+    assert!(!readable(&r));
+    assert_eq!(Ok(()), s.send(1).now());
+    assert!(readable(&r));
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(Ok(None), r.receive().now());
+    assert!(!readable(&r));
+    assert_eq!(Ok(()), s.send(2).now());
+    assert!(readable(&r));
+    assert_eq!(Ok(()), s.send(3).now());
+    assert!(!readable(&s));
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 4 }), s.send(4).now());
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert!(readable(&s));
+}
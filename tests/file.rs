@@ -0,0 +1,76 @@
+#![cfg(feature="file")]
+use async_spsc::*;
+use async_spsc::file;
+use async_spsc::shm::ShmError;
+use std::ffi::CString;
+
+// A fresh path in the temporary directory, unique to this process.
+fn path(test: &str) -> CString {
+    let mut path = std::env::temp_dir();
+    path.push(format!("async-spsc-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_file(&path);
+    CString::new(path.into_os_string().into_string().unwrap()).unwrap()
+}
+
+fn remove(path: &CString) {
+    std::fs::remove_file(path.to_str().unwrap()).unwrap();
+}
+
+#[test]
+fn create_exists() {
+    let path = path("create_exists");
+    let _c = file::create::<u64>(&path, 1).unwrap();
+    assert_eq!(Some(ShmError::Os(libc::EEXIST)), file::create::<u64>(&path, 1).err());
+    remove(&path);
+}
+
+#[test]
+fn recover_missing() {
+    let path = path("recover_missing");
+    assert_eq!(Some(ShmError::Os(libc::ENOENT)), unsafe { file::recover::<u64>(&path) }.err());
+}
+
+#[test]
+fn recover_wrong_layout() {
+    let path = path("recover_wrong_layout");
+    drop(file::create::<u64>(&path, 1).unwrap());
+    assert_eq!(Some(ShmError::Layout), unsafe { file::recover::<u32>(&path) }.err());
+    remove(&path);
+}
+
+#[test]
+fn recover_closed() {
+    let path = path("recover_closed");
+    let (mut s, mut r) = file::create::<u64>(&path, 3).unwrap();
+    assert_eq!(Ok(()), s.send(1).now());
+    assert_eq!(Ok(()), s.send(2).now());
+    assert_eq!(Ok(()), s.send(3).now());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    drop(s);
+    drop(r);
+    let (mut s, mut r) = unsafe { file::recover::<u64>(&path) }.unwrap();
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Ok(()), s.send(4).now());
+    assert_eq!(Ok(()), s.send(5).now());
+    assert_eq!(Ok(Some(3)), r.receive().now());
+    assert_eq!(Ok(Some(4)), r.receive().now());
+    assert_eq!(Ok(Some(5)), r.receive().now());
+    assert_eq!(Ok(None), r.receive().now());
+    remove(&path);
+}
+
+#[test]
+fn recover_crashed() {
+    let path = path("recover_crashed");
+    let (mut s, r) = file::create::<u64>(&path, 2).unwrap();
+    assert_eq!(Ok(()), s.send(42).now());
+    // Neither side gets to close.
+    std::mem::forget((s, r));
+    let (mut s, mut r) = unsafe { file::recover::<u64>(&path) }.unwrap();
+    assert_eq!(Ok(()), s.send(420).now());
+    assert_eq!(Ok(Some(42)), r.receive().now());
+    assert_eq!(Ok(Some(420)), r.receive().now());
+    drop(s);
+    assert_eq!(Err(Closed), r.receive().now());
+    remove(&path);
+}
@@ -0,0 +1,116 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+mod common;
+use common::*;
+
+#[test]
+fn conflate() {
+    let (mut s, mut r) = latest::<i32>();
+    assert_eq!(Ok(None), r.receive().now());
+    for i in 0..5 { assert_eq!(Ok(()), s.send(i)); }
+    assert_eq!(Ok(Some(4)), r.receive().now());
+    assert_eq!(Ok(None), r.receive().now());
+    for i in 5..7 {
+        assert_eq!(Ok(()), s.send(i));
+        assert_eq!(Ok(Some(i)), r.receive().now());
+    }
+}
+
+#[test]
+fn drops() {
+    for receiver_first in [false, true] {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut s, mut r) = latest::<Drops>();
+        for _ in 0..3 { assert!(s.send(Drops(drops.clone())).is_ok()); }
+        // The replaced are dropped as they go.
+        assert_eq!(2, drops.load(Ordering::Relaxed));
+        drop(r.receive().now());
+        assert_eq!(3, drops.load(Ordering::Relaxed));
+        assert!(s.send(Drops(drops.clone())).is_ok());
+        if receiver_first {
+            drop(r);
+            let e = s.send(Drops(drops.clone())).err().unwrap();
+            assert_eq!(SendErrorKind::Closed, e.kind);
+            drop(e);
+            assert_eq!(4, drops.load(Ordering::Relaxed));
+            drop(s);
+        } else {
+            drop(s);
+            drop(r);
+        }
+        assert_eq!(if receiver_first { 5 } else { 4 }, drops.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn close() {
+    let (mut s, mut r) = latest::<i32>();
+    s.send(1).unwrap();
+    s.send(2).unwrap();
+    drop(s);
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Err(Closed), r.receive().now());
+    let (s, mut r) = latest::<i32>();
+    drop(s);
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn async_receive() {
+    let (mut s, mut r) = latest::<i32>();
+    {
+        wookie!(r2: r.receive());
+        assert_eq!(Poll::Pending, r2.poll());
+        r2.stats().assert(2, 0, 0);
+        s.send(1).unwrap();
+        r2.stats().assert(2, 1, 1);
+        s.send(2).unwrap();
+        assert_eq!(Poll::Ready(Ok(2)), r2.poll());
+    }
+    wookie!(r2: r.receive());
+    assert_eq!(Poll::Pending, r2.poll());
+    drop(s);
+    assert_eq!(Poll::Ready(Err(Closed)), r2.poll());
+}
+
+#[test]
+#[cfg(feature="std")]
+fn park() {
+    let (mut s, mut r) = latest_with::<usize, _>(Park::default());
+    let t = std::thread::spawn(move || {
+        for i in 0..1000 {
+            s.send(i).unwrap();
+            if i % 100 == 0 { std::thread::yield_now(); }
+        }
+    });
+    let mut last = None;
+    while let Ok(v) = r.receive().wait() {
+        assert!(last.is_none_or(|last| v > last));
+        last = Some(v);
+    }
+    t.join().unwrap();
+    assert_eq!(Some(999), last);
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 100_000;
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, mut r) = latest::<(usize, Drops)>();
+    let d = drops.clone();
+    let t = std::thread::spawn(move || {
+        for i in 0..COUNT { assert!(s.send((i, Drops(d.clone()))).is_ok()); }
+    });
+    let mut last = None;
+    receive_all(|| r.receive().now(), |(v, _)| {
+        assert!(last.is_none_or(|last| v > last));
+        last = Some(v);
+    });
+    t.join().unwrap();
+    // We always get the last.
+    assert_eq!(Some(COUNT - 1), last);
+    assert_eq!(COUNT, drops.load(Ordering::Relaxed));
+}
@@ -0,0 +1,135 @@
+use async_spsc::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+mod common;
+use common::*;
+
+#[test]
+fn padded() {
+    assert!(core::mem::align_of::<CachePadded<u8>>() >= 64);
+    let (mut s, mut r) = spsc_padded::<i32>(2);
+    assert_eq!(Ok(()), s.send(1).now());
+    assert_eq!(Ok(()), s.send(2).now());
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 3 }), s.send(3).now());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(Ok(()), s.send(3).now());
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Ok(Some(3)), r.receive().now());
+    assert_eq!(Ok(None), r.receive().now());
+    drop(s);
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn padded_slots() {
+    let (mut s, mut r) = spsc_padded::<CachePadded<u64>>(4);
+    for i in 0..4 { s.send(CachePadded::new(i)).now().unwrap(); }
+    for i in 0..4 { assert_eq!(Ok(Some(CachePadded::new(i))), r.receive().now()); }
+}
+
+#[test]
+fn padded_drop_in_flight() {
+    for receiver_first in [false, true] {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut s, mut r) = spsc_padded::<Drops>(4);
+        for _ in 0..3 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        drop(r.receive().now());
+        assert_eq!(1, drops.load(Ordering::Relaxed));
+        if receiver_first {
+            drop(r);
+            drop(s);
+        } else {
+            drop(s);
+            drop(r);
+        }
+        assert_eq!(3, drops.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn padded_reclaim() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, r) = spsc_padded::<Drops>(4);
+    for _ in 0..3 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+    assert!(s.reclaim().is_none());
+    drop(r);
+    drop(s.reclaim());
+    assert_eq!(1, drops.load(Ordering::Relaxed));
+    drop(s);
+    assert_eq!(3, drops.load(Ordering::Relaxed));
+}
+
+#[test]
+fn padded_threads() {
+    let (mut s, mut r) = spsc_padded::<usize>(4);
+    let t = std::thread::spawn(move || {
+        for i in 0..100_000 { send_or_yield(|v| s.send(v).now(), i).unwrap(); }
+    });
+    let mut next = 0;
+    receive_all(|| r.receive().now(), |v| {
+        assert_eq!(next, v);
+        next += 1;
+    });
+    t.join().unwrap();
+    assert_eq!(100_000, next);
+}
+
+#[cfg(feature="std")]
+#[test]
+fn padded_park() {
+    let (mut s, mut r) = spsc_padded_with::<usize, _>(1, Park::default());
+    let t = std::thread::spawn(move || {
+        for i in 0..10000 { s.send(i).wait().unwrap(); }
+    });
+    for i in 0..10000 { assert_eq!(Ok(i), r.receive().wait()); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().wait());
+}
+
+// The Receiver goes by its copy of where the Sender is until it runs
+// out, but must not take that as final when the Sender closes.
+#[test]
+fn padded_close_behind() {
+    let (mut s, mut r) = spsc_padded::<i32>(2);
+    s.send(0).now().unwrap();
+    s.send(1).now().unwrap();
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    s.send(2).now().unwrap();
+    drop(s);
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[cfg(feature="async")]
+#[test]
+fn padded_await() {
+    let (mut s, mut r) = spsc_padded::<usize>(2);
+    let t = std::thread::spawn(move || {
+        for i in 0..10000 { block_on(s.send(i)).unwrap(); }
+    });
+    for i in 0..10000 { assert_eq!(Ok(i), block_on(r.receive())); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), block_on(r.receive()));
+}
+
+// The Sender must look at where the Receiver is once it has gone
+// idle, even though it keeps publishing without needing to.
+#[cfg(feature="async")]
+#[test]
+fn padded_wait_len() {
+    let (mut s, mut r) = spsc_padded::<usize>(8);
+    let t = std::thread::spawn(move || {
+        for i in 0..1000 { block_on(s.send(i)).unwrap(); }
+    });
+    let mut next = 0;
+    while next < 1000 {
+        assert!(block_on(r.wait_len(4)).unwrap() >= 4);
+        for _ in 0..4 {
+            assert_eq!(Ok(Some(next)), r.receive().now());
+            next += 1;
+        }
+    }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().now());
+}
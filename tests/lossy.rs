@@ -0,0 +1,141 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+mod common;
+use common::*;
+
+#[test]
+fn overwrite() {
+    let (mut s, mut r) = spsc_lossy::<i32>(3);
+    assert_eq!(3, s.capacity());
+    for i in 0..3 { assert_eq!(Ok(()), s.send(i).now()); }
+    assert!(s.is_full());
+    assert_eq!(0, r.dropped());
+    assert_eq!(Ok(()), s.send(3).now());
+    assert_eq!(Ok(()), s.send(4).now());
+    assert_eq!(2, r.dropped());
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Ok(()), s.send(5).now());
+    assert_eq!(2, r.dropped());
+    for i in 3..6 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+}
+
+#[test]
+fn wrap_around() {
+    for cap in [1, 2, 5, 8] {
+        let (mut s, mut r) = spsc_lossy::<usize>(cap);
+        let mut next = 0;
+        for i in 0..cap as usize * 7 {
+            s.send(i).now().unwrap();
+            if i % 5 == 4 {
+                while let Ok(Some(v)) = r.receive().now() {
+                    assert!(v >= next);
+                    next = v + 1;
+                }
+                // Only the newest survive.
+                assert_eq!(i + 1, next);
+            }
+        }
+    }
+}
+
+// Half the usual maximum, to leave room for the reading flag.
+#[test]
+#[should_panic]
+#[cfg(target_pointer_width="64")]
+fn too_big() { spsc_lossy::<i32>(1 << 29); }
+
+// Nor can more than the capacity ever be queued, whatever the ring.
+#[test]
+#[should_panic]
+fn min_batch_too_big() { spsc_lossy::<i32>(2).1.set_min_batch(3); }
+
+#[test]
+#[should_panic]
+fn wait_len_too_big() { drop(spsc_lossy::<i32>(2).1.wait_len(3)); }
+
+// The spare slot is no space to the Sender, so it isn't woken for it.
+#[test]
+fn min_space() {
+    let (mut s, mut r) = spsc_lossy::<i32>(2);
+    s.set_min_space(2);
+    for i in 0..2 { s.send(i).now().unwrap(); }
+    wookie!(s2: s.wait_space(2));
+    assert_eq!(Poll::Pending, s2.poll());
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    assert_eq!(0, s2.woken());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(1, s2.woken());
+    assert_eq!(Poll::Ready(Ok(2)), s2.poll());
+}
+
+#[test]
+fn drops() {
+    for receiver_first in [false, true] {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut s, mut r) = spsc_lossy::<Drops>(2);
+        for _ in 0..5 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        // The evicted are dropped as they go.
+        assert_eq!(3, drops.load(Ordering::Relaxed));
+        assert_eq!(3, r.dropped());
+        drop(r.receive().now());
+        assert_eq!(4, drops.load(Ordering::Relaxed));
+        if receiver_first {
+            drop(r);
+            assert!(s.send(Drops(drops.clone())).now().is_err());
+            drop(s);
+        } else {
+            drop(s);
+            drop(r);
+        }
+        assert_eq!(if receiver_first { 6 } else { 5 }, drops.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn close() {
+    let (mut s, mut r) = spsc_lossy::<i32>(2);
+    for i in 0..4 { s.send(i).now().unwrap(); }
+    drop(s);
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Ok(Some(3)), r.receive().now());
+    assert_eq!(Err(Closed), r.receive().now());
+    assert_eq!(2, r.dropped());
+}
+
+#[test]
+fn async_receive() {
+    let (mut s, mut r) = spsc_lossy::<i32>(1);
+    wookie!(r2: r.receive());
+    assert_eq!(Poll::Pending, r2.poll());
+    s.send(1).now().unwrap();
+    s.send(2).now().unwrap();
+    assert_eq!(Poll::Ready(Ok(2)), r2.poll());
+    // Sending never waits.
+    wookie!(s2: s.send(3));
+    assert_eq!(Poll::Ready(Ok(())), s2.poll());
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 100_000;
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, mut r) = spsc_lossy::<(usize, Drops)>(4);
+    let d = drops.clone();
+    let t = std::thread::spawn(move || {
+        for i in 0..COUNT { assert!(s.send((i, Drops(d.clone()))).now().is_ok()); }
+    });
+    let (mut received, mut next) = (0, 0);
+    receive_all(|| r.receive().now(), |(v, _)| {
+        assert!(v >= next);
+        next = v + 1;
+        received += 1;
+    });
+    t.join().unwrap();
+    // Whatever we did not receive was dropped, and only once.
+    assert_eq!(COUNT, received + r.dropped());
+    assert_eq!(COUNT, drops.load(Ordering::Relaxed));
+}
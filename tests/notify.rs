@@ -0,0 +1,79 @@
+use async_spsc::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn noop() {
+    let (mut s, mut r) = spsc_with::<i32, _>(1, NoOp);
+    assert_eq!(Ok(()), s.send(1).now());
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 2 }), s.send(2).now());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(Ok(None), r.receive().now());
+    drop(s);
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn callback() {
+    let senders = Arc::new(AtomicUsize::new(0));
+    let receivers = Arc::new(AtomicUsize::new(0));
+    let (s2, r2) = (senders.clone(), receivers.clone());
+    let (mut s, mut r) = spsc_with::<i32, _>(2, Callback(move |side| match side {
+        Side::Sender => { s2.fetch_add(1, Ordering::Relaxed); }
+        Side::Receiver => { r2.fetch_add(1, Ordering::Relaxed); }
+    }));
+    // Only the transitions out of idle are notified.
+    assert_eq!(Ok(()), s.send(1).now());
+    assert_eq!(Ok(()), s.send(2).now());
+    assert_eq!(1, receivers.load(Ordering::Relaxed));
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(1, senders.load(Ordering::Relaxed));
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(1, senders.load(Ordering::Relaxed));
+    assert_eq!(Ok(()), s.send(3).now());
+    assert_eq!(2, receivers.load(Ordering::Relaxed));
+    // Not idle, so no need.
+    drop(s);
+    assert_eq!(2, receivers.load(Ordering::Relaxed));
+    assert_eq!(Ok(Some(3)), r.receive().now());
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[cfg(feature="std")]
+#[test]
+fn park() {
+    let (mut s, mut r) = spsc_with::<usize, _>(1, Park::default());
+    let t = std::thread::spawn(move || {
+        for i in 0..10000 { s.send(i).wait().unwrap(); }
+    });
+    for i in 0..10000 { assert_eq!(Ok(i), r.receive().wait()); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().wait());
+}
+
+#[cfg(feature="std")]
+#[test]
+fn park_close() {
+    let (s, mut r) = spsc_with::<usize, _>(1, Park::default());
+    let t = std::thread::spawn(move || r.receive().wait());
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    drop(s);
+    assert_eq!(Err(Closed), t.join().unwrap());
+}
+
+#[test]
+fn callback_min_batch() {
+    let receivers = Arc::new(AtomicUsize::new(0));
+    let r2 = receivers.clone();
+    let (mut s, mut r) = spsc_with::<i32, _>(8, Callback(move |side| {
+        if side == Side::Receiver { r2.fetch_add(1, Ordering::Relaxed); }
+    }));
+    r.set_min_batch(3);
+    // Only crossing the watermark is notified.
+    for i in 0..5 { assert_eq!(Ok(()), s.send(i).now()); }
+    assert_eq!(1, receivers.load(Ordering::Relaxed));
+    for i in 0..5 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+    for i in 0..3 { assert_eq!(Ok(()), s.send(i).now()); }
+    assert_eq!(2, receivers.load(Ordering::Relaxed));
+}
@@ -0,0 +1,68 @@
+use async_spsc::*;
+use wookie::*;
+use core::future::Future;
+use core::task::*;
+mod common;
+use common::*;
+
+fn is_static<F: Future + Send + 'static>(f: F) -> F { f }
+
+#[test]
+fn handback() {
+    let (mut s, r) = spsc::<i32>(1);
+    s.send(1).now().unwrap();
+    let r2 = is_static(r.into_recv());
+    wookie!(r2);
+    let (mut r, ret) = match r2.poll() {
+        Poll::Ready(ready) => ready,
+        Poll::Pending => panic!("it was sent"),
+    };
+    assert_eq!(Ok(1), ret);
+    // We can carry on with it as before.
+    assert_eq!(Ok(None), r.receive().now());
+    let r2 = r.into_recv();
+    wookie!(r2);
+    assert!(r2.poll().is_pending());
+    let s2 = is_static(s.into_send(2));
+    wookie!(s2);
+    let (_s, ret) = match s2.poll() {
+        Poll::Ready(ready) => ready,
+        Poll::Pending => panic!("there is space"),
+    };
+    assert!(ret.is_ok());
+    r2.stats().assert(2, 1, 1);
+    assert!(matches!(r2.poll(), Poll::Ready((_, Ok(2)))));
+}
+
+#[test]
+fn closed() {
+    let (s, r) = spsc::<i32>(1);
+    drop(r);
+    let (_s, ret) = block_on(s.into_send(1));
+    assert_eq!(SendErrorKind::Closed, ret.unwrap_err().kind);
+    let (s, r) = spsc::<i32>(1);
+    drop(s);
+    let (_r, ret) = block_on(r.into_recv());
+    assert_eq!(Err(Closed), ret);
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 10_000;
+    let (mut s, mut r) = spsc::<usize>(4);
+    // Each future is moved onto a thread of its own.
+    let t = std::thread::spawn(move || {
+        for i in 0..COUNT {
+            let f = s.into_send(i);
+            let (s2, ret) = std::thread::spawn(move || block_on(f)).join().unwrap();
+            assert!(ret.is_ok());
+            s = s2;
+        }
+    });
+    for i in 0..COUNT {
+        let (r2, ret) = block_on(r.into_recv());
+        assert_eq!(Ok(i), ret);
+        r = r2;
+    }
+    t.join().unwrap();
+}
@@ -0,0 +1,99 @@
+use async_spsc::*;
+use wookie::*;
+use core::future::poll_fn;
+use core::task::*;
+mod common;
+use common::*;
+
+#[test]
+fn recv() {
+    let (mut s, mut r) = spsc::<i32>(2);
+    {
+        // Each poll is a fresh future, as if from a wrapper's own.
+        wookie!(r2: poll_fn(|ctx| r.poll_recv(ctx)));
+        assert_eq!(Poll::Pending, r2.poll());
+        // Only the shared slot holds on to the waker.
+        r2.stats().assert(2, 1, 0);
+        s.try_send(1).unwrap();
+        r2.stats().assert(2, 2, 1);
+        assert_eq!(Poll::Ready(Ok(1)), r2.poll());
+    }
+    drop(s);
+    wookie!(r2: poll_fn(|ctx| r.poll_recv(ctx)));
+    assert_eq!(Poll::Ready(Err(Closed)), r2.poll());
+}
+
+#[test]
+fn ready() {
+    let (mut s, mut r) = spsc::<i32>(1);
+    s.try_send(1).unwrap();
+    assert_eq!(SendErrorKind::Full, s.try_send(2).unwrap_err().kind);
+    {
+        wookie!(s2: poll_fn(|ctx| s.poll_ready(ctx)));
+        assert_eq!(Poll::Pending, s2.poll());
+        assert_eq!(Ok(Some(1)), r.receive().now());
+        s2.stats().assert(2, 2, 1);
+        assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    }
+    s.try_send(2).unwrap();
+    drop(r);
+    wookie!(s2: poll_fn(|ctx| s.poll_ready(ctx)));
+    assert_eq!(Poll::Ready(Err(Closed)), s2.poll());
+}
+
+// Once ready, we clear the waker an earlier poll left registered.
+#[test]
+fn clears_waker() {
+    let (mut s, mut r) = spsc::<i32>(2);
+    r.set_min_batch(2);
+    wookie!(r2: poll_fn(|ctx| r.poll_recv(ctx)));
+    assert_eq!(Poll::Pending, r2.poll());
+    // Too few to wake us for.
+    s.try_send(1).unwrap();
+    r2.stats().assert(2, 1, 0);
+    assert_eq!(Poll::Ready(Ok(1)), r2.poll());
+    r2.stats().assert(2, 2, 0);
+    s.set_min_space(2);
+    s.try_send(2).unwrap();
+    s.try_send(3).unwrap();
+    wookie!(s2: poll_fn(|ctx| s.poll_ready(ctx)));
+    assert_eq!(Poll::Pending, s2.poll());
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    s2.stats().assert(2, 1, 0);
+    assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    s2.stats().assert(2, 2, 0);
+}
+
+#[test]
+fn never_full() {
+    let (mut s, _r) = spsc_lossy::<i32>(1);
+    s.try_send(1).unwrap();
+    wookie!(s2: poll_fn(|ctx| s.poll_ready(ctx)));
+    assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    let (mut s, r) = unbounded_spsc::<i32>(1);
+    s.try_send(1).unwrap();
+    {
+        wookie!(s2: poll_fn(|ctx| s.poll_ready(ctx)));
+        assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    }
+    drop(r);
+    wookie!(s2: poll_fn(|ctx| s.poll_ready(ctx)));
+    assert_eq!(Poll::Ready(Err(Closed)), s2.poll());
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 100_000;
+    let (mut s, mut r) = spsc::<usize>(4);
+    let t = std::thread::spawn(move || {
+        for i in 0..COUNT {
+            block_on(poll_fn(|ctx| s.poll_ready(ctx))).unwrap();
+            s.try_send(i).unwrap();
+        }
+    });
+    for i in 0..COUNT {
+        assert_eq!(Ok(i), block_on(poll_fn(|ctx| r.poll_recv(ctx))));
+    }
+    assert_eq!(Err(Closed), block_on(poll_fn(|ctx| r.poll_recv(ctx))));
+    t.join().unwrap();
+}
@@ -0,0 +1,128 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+mod common;
+use common::*;
+
+#[test]
+fn now() {
+    let (mut s, mut r) = spsc::<i32>(0);
+    assert_eq!(0, s.capacity());
+    // Nobody takes it, so we get it back.
+    let e = s.send(1).now().unwrap_err();
+    assert_eq!((SendErrorKind::Full, 1), (e.kind, e.value));
+    assert_eq!(Ok(None), r.receive().now());
+    drop(r);
+    assert_eq!(SendErrorKind::Closed, s.send(2).now().unwrap_err().kind);
+}
+
+#[test]
+fn handover() {
+    let (mut s, mut r) = spsc::<i32>(0);
+    {
+        wookie!(s2: s.send(1));
+        // It is there to be taken, but we are not done yet.
+        assert_eq!(Poll::Pending, s2.poll());
+        s2.stats().assert(2, 0, 0);
+        assert_eq!(Ok(Some(1)), r.receive().now());
+        s2.stats().assert(2, 1, 1);
+        assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    }
+    wookie!(r2: r.receive());
+    assert_eq!(Poll::Pending, r2.poll());
+    {
+        wookie!(s2: s.send(2));
+        assert_eq!(Poll::Pending, s2.poll());
+        assert_eq!(Poll::Ready(Ok(2)), r2.poll());
+        assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    }
+}
+
+#[test]
+fn cancel() {
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, mut r) = spsc::<Drops>(0);
+    {
+        wookie!(s2: s.send(Drops(drops.clone())));
+        assert!(s2.poll().is_pending());
+    }
+    // Giving up takes it back.
+    assert_eq!(1, drops.load(Ordering::Relaxed));
+    assert!(r.receive().now().unwrap().is_none());
+    {
+        wookie!(s2: s.send(Drops(drops.clone())));
+        assert!(s2.poll().is_pending());
+        drop(r);
+        match s2.poll() {
+            Poll::Ready(Err(e)) => assert_eq!(SendErrorKind::Closed, e.kind),
+            _ => panic!("expected it back"),
+        }
+    }
+    assert_eq!(2, drops.load(Ordering::Relaxed));
+}
+
+#[test]
+#[cfg(feature="std")]
+fn park() {
+    let (mut s, mut r) = spsc_with::<usize, _>(0, Park::default());
+    let sent = Arc::new(AtomicUsize::new(0));
+    let s2 = sent.clone();
+    let t = std::thread::spawn(move || {
+        for i in 0..1000 {
+            s.send(i).wait().unwrap();
+            s2.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    for i in 0..1000 {
+        assert_eq!(Ok(i), r.receive().wait());
+        // The Sender is never more than one ahead.
+        assert!(sent.load(Ordering::SeqCst) <= i + 1);
+    }
+    t.join().unwrap();
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 100_000;
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, mut r) = spsc::<(usize, Drops)>(0);
+    let d = drops.clone();
+    let t = std::thread::spawn(move || {
+        let mut sent = 0;
+        for i in 0..COUNT {
+            // Either it is taken or we get it back.
+            if s.send((i, Drops(d.clone()))).now().is_ok() { sent += 1; }
+        }
+        sent
+    });
+    let (mut received, mut last) = (0, None);
+    receive_all(|| r.receive().now(), |(v, _)| {
+        assert!(last.is_none_or(|last| v > last));
+        last = Some(v);
+        received += 1;
+    });
+    // Exactly those we were told were taken were.
+    assert_eq!(t.join().unwrap(), received);
+    assert_eq!(COUNT, drops.load(Ordering::Relaxed));
+}
+
+#[test]
+fn wait_len() {
+    let (mut s, mut r) = spsc::<i32>(0);
+    wookie!(w: r.wait_len(1));
+    assert_eq!(Poll::Pending, w.poll());
+    wookie!(s2: s.send(1));
+    assert_eq!(Poll::Pending, s2.poll());
+    // A waiting send is the one message there can be.
+    assert_eq!(Poll::Ready(Ok(1)), w.poll());
+}
+
+#[test]
+#[should_panic]
+fn wait_len_too_big() { drop(spsc::<i32>(0).1.wait_len(2)); }
+
+#[test]
+#[should_panic]
+fn min_batch_too_big() { spsc::<i32>(0).1.set_min_batch(2); }
@@ -0,0 +1,159 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::convert::TryInto;
+mod common;
+use common::*;
+
+#[test]
+fn grow() {
+    let (mut s, mut r) = spsc_resizable::<i32>(2);
+    for i in 0..2 { s.send(i).now().unwrap(); }
+    assert_eq!(SendErrorKind::Full, s.send(2).now().unwrap_err().kind);
+    assert_eq!(Ok(()), s.grow(5));
+    assert_eq!(5, s.capacity());
+    // The two still in the old ring take up room in the new one.
+    assert_eq!(3, s.space());
+    for i in 2..5 { s.send(i).now().unwrap(); }
+    assert_eq!(SendErrorKind::Full, s.send(5).now().unwrap_err().kind);
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    assert_eq!(SendErrorKind::Full, s.send(5).now().unwrap_err().kind);
+    // Until the Receiver has moved on.
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    for i in 5..7 { s.send(i).now().unwrap(); }
+    assert!(s.is_full());
+    for i in 2..7 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+    // The Receiver has moved on too.
+    r.set_min_batch(5);
+}
+
+#[test]
+fn shrink() {
+    let (mut s, mut r) = spsc_resizable::<i32>(4);
+    for i in 0..4 { s.send(i).now().unwrap(); }
+    // There are more in flight than would fit.
+    assert_eq!(Err(ResizeError::Full), s.shrink(3));
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    assert_eq!(Ok(()), s.shrink(3));
+    assert_eq!(3, s.capacity());
+    assert!(s.is_full());
+    for i in 1..4 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+    s.send(4).now().unwrap();
+    assert_eq!(Ok(()), s.shrink(1));
+    assert!(s.send(5).now().is_err());
+    assert_eq!(Ok(Some(4)), r.receive().now());
+    // Over and over, in both directions.
+    let mut next = 5;
+    for cap in [3, 1, 8, 2, 4, 5] {
+        if cap >= s.capacity() { s.grow(cap).unwrap() } else { s.shrink(cap).unwrap() }
+        for _ in 0..cap { s.send(next).now().unwrap(); next += 1; }
+        // Only the Receiver can make room for the next round.
+        assert!(s.send(next).now().is_err());
+        for i in next - cap as i32..next { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    }
+}
+
+// However it is resized, no more than the capacity are ever in flight.
+#[test]
+fn bounded() {
+    let (mut s, mut r) = spsc_resizable::<i32>(2);
+    let (mut sent, mut received) = (0, 0);
+    for cap in [4, 6, 8, 3, 2, 5] {
+        let ret = if cap >= s.capacity() { s.grow(cap) } else { s.shrink(cap) };
+        assert!(ret.is_ok() || ret == Err(ResizeError::Full));
+        while s.send(sent).now().is_ok() { sent += 1; }
+        assert!(sent - received <= s.capacity() as i32);
+        // Take some, but not all.
+        for _ in 0..2 {
+            assert_eq!(Ok(Some(received)), r.receive().now());
+            received += 1;
+        }
+    }
+    while let Ok(Some(v)) = r.receive().now() {
+        assert_eq!(received, v);
+        received += 1;
+    }
+    assert_eq!(sent, received);
+}
+
+#[test]
+fn not_resizable() {
+    let (mut s, _r) = spsc::<i32>(2);
+    assert_eq!(Err(ResizeError::NotResizable), s.grow(4));
+    assert_eq!(Err(ResizeError::NotResizable), s.shrink(1));
+    let (mut s, _r) = unbounded_spsc::<i32>(2);
+    assert_eq!(Err(ResizeError::NotResizable), s.grow(4));
+}
+
+#[test]
+fn drops() {
+    for receiver_first in [false, true] {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut s, r) = spsc_resizable::<Drops>(2);
+        for _ in 0..2 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        // Each ring starts with room taken for those before it, which
+        // must not be dropped as if there were messages there.
+        s.grow(4).unwrap();
+        for _ in 0..2 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        s.grow(6).unwrap();
+        for _ in 0..2 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        assert!(s.is_full());
+        assert_eq!(0, drops.load(Ordering::Relaxed));
+        if receiver_first {
+            // Each ring it leaves behind is cleaned up at its own size.
+            drop(r);
+            assert_eq!(4, drops.load(Ordering::Relaxed));
+            assert_eq!(Err(ResizeError::Closed), s.grow(8));
+            drop(s);
+        } else {
+            drop(s);
+            drop(r);
+        }
+        assert_eq!(6, drops.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn async_receive() {
+    let (mut s, mut r) = spsc_resizable::<i32>(1);
+    wookie!(r2: r.receive());
+    assert_eq!(Poll::Pending, r2.poll());
+    // Sealing the old ring wakes it to move on.
+    s.grow(2).unwrap();
+    r2.stats().assert(2, 1, 1);
+    assert_eq!(Poll::Pending, r2.poll());
+    s.send(1).now().unwrap();
+    assert_eq!(Poll::Ready(Ok(1)), r2.poll());
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 100_000;
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, mut r) = spsc_resizable::<(usize, Drops)>(4);
+    let d = drops.clone();
+    let t = std::thread::spawn(move || {
+        for i in 0..COUNT {
+            if i % 1000 == 0 {
+                let cap = (1 + (i / 1000) % 16).try_into().unwrap();
+                if cap >= s.capacity() { s.grow(cap).unwrap() } else {
+                    // Until the Receiver has taken enough for it to fit.
+                    while s.shrink(cap) == Err(ResizeError::Full) { std::thread::yield_now(); }
+                }
+            }
+            assert!(send_or_yield(|v| s.send(v).now(), (i, Drops(d.clone()))).is_ok());
+        }
+    });
+    let mut next = 0;
+    receive_all(|| r.receive().now(), |(v, _)| {
+        assert_eq!(next, v);
+        next += 1;
+    });
+    t.join().unwrap();
+    assert_eq!(COUNT, next);
+    assert_eq!(COUNT, drops.load(Ordering::Relaxed));
+}
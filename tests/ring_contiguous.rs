@@ -1,3 +1,5 @@
+// wookie's macros have since become safe to use.
+#![allow(unused_unsafe)]
 use async_spsc::*;
 use wookie::*;
 use core::task::*;
@@ -47,25 +49,27 @@ fn ping_pong_async_async() {
         for _ in 0..10 {
             {
                 wookie!(r2: r.receive());
+                // One clone to register, one to remember it by.
                 assert_eq!(Poll::Pending, r2.poll());
-                r2.stats().assert(1, 0, 0);
+                r2.stats().assert(2, 0, 0);
+                // Still registered, so nothing to do.
                 assert_eq!(Poll::Pending, r2.poll());
-                r2.stats().assert(2, 1, 0);
+                r2.stats().assert(2, 0, 0);
                 {
                     wookie!(s2: s.send(42));
                     assert_eq!(Poll::Ready(Ok(())), s2.poll());
                     s2.stats().assert(0, 0, 0);
                 }
-                r2.stats().assert(2, 2, 1);
+                r2.stats().assert(2, 1, 1);
                 wookie!(s2: s.send(420));
                 assert_eq!(Poll::Pending, s2.poll());
-                r2.stats().assert(2, 2, 1);
-                s2.stats().assert(1, 0, 0);
+                r2.stats().assert(2, 1, 1);
+                s2.stats().assert(2, 0, 0);
                 assert_eq!(Poll::Ready(Ok(42)), r2.poll());
-                r2.stats().assert(2, 2, 1);
-                s2.stats().assert(1, 1, 1);
+                r2.stats().assert(2, 1, 1);
+                s2.stats().assert(2, 1, 1);
                 assert_eq!(Poll::Ready(Ok(())), s2.poll());
-                s2.stats().assert(1, 1, 1);
+                s2.stats().assert(2, 1, 1);
             }
             wookie!(r2: r.receive());
             assert_eq!(Poll::Ready(Ok(420)), r2.poll());
@@ -82,11 +86,11 @@ fn ping_pong_sync_async() {
             {
                 wookie!(r2: r.receive());
                 assert_eq!(Poll::Pending, r2.poll());
-                r2.stats().assert(1, 0, 0);
+                r2.stats().assert(2, 0, 0);
                 assert_eq!(Poll::Pending, r2.poll());
-                r2.stats().assert(2, 1, 0);
+                r2.stats().assert(2, 0, 0);
                 assert_eq!(Ok(()), s.send(42).now());
-                r2.stats().assert(2, 2, 1);
+                r2.stats().assert(2, 1, 1);
                 assert_eq!(full(420), s.send(420).now());
                 assert_eq!(Poll::Ready(Ok(42)), r2.poll());
             }
@@ -112,11 +116,11 @@ fn ping_pong_async_sync() {
             {
                 wookie!(s2: s.send(420));
                 assert_eq!(Poll::Pending, s2.poll());
-                s2.stats().assert(1, 0, 0);
+                s2.stats().assert(2, 0, 0);
                 assert_eq!(Ok(Some(42)), r.receive().now());
-                s2.stats().assert(1, 1, 1);
+                s2.stats().assert(2, 1, 1);
                 assert_eq!(Poll::Ready(Ok(())), s2.poll());
-                s2.stats().assert(1, 1, 1);
+                s2.stats().assert(2, 1, 1);
                 assert_eq!(Ok(Some(420)), r.receive().now());
             }
         }
@@ -151,11 +155,11 @@ fn send_drop() {
         }
         wookie!(s2: s.send(42));
         assert_eq!(Poll::Pending, s2.poll());
-        cdw!(s2: 1, 0, 0);
+        cdw!(s2: 2, 0, 0);
         drop(r);
-        cdw!(s2: 1, 1, 1);
+        cdw!(s2: 2, 1, 1);
         assert_eq!(Poll::Ready(closed(42)), s2.poll());
-        cdw!(s2: 1, 1, 1);
+        cdw!(s2: 2, 1, 1);
     }
 }
 
@@ -197,3 +201,142 @@ fn send_drop_receive() {
     wookie!(r2: r.receive());
     assert_eq!(Poll::Ready(Err(Closed)), r2.poll());
 }
+
+#[test]
+fn reclaim() {
+    let (mut s, mut r) = spsc::<i32>(3);
+    assert_eq!(Ok(()), s.send(1).now());
+    assert_eq!(Ok(()), s.send(2).now());
+    assert_eq!(Ok(()), s.send(3).now());
+    assert_eq!(None, s.reclaim());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    drop(r);
+    assert_eq!(Some(2), s.reclaim());
+    assert_eq!(closed(4), s.send(4).now());
+    assert_eq!(Some(3), s.reclaim());
+    assert_eq!(None, s.reclaim());
+}
+
+#[test]
+fn flush_threshold() {
+    let (mut s, mut r) = spsc::<i32>(8);
+    s.set_flush_threshold(3);
+    s.send(1).now().unwrap();
+    s.send(2).now().unwrap();
+    assert_eq!(Ok(None), r.receive().now());
+    s.send(3).now().unwrap();
+    for i in 1..4 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    s.send(4).now().unwrap();
+    assert_eq!(Ok(None), r.receive().now());
+    s.flush().unwrap();
+    assert_eq!(Ok(Some(4)), r.receive().now());
+    s.flush().unwrap();
+}
+
+#[test]
+fn flush_full() {
+    let (mut s, mut r) = spsc::<i32>(2);
+    s.set_flush_threshold(2);
+    s.send(1).now().unwrap();
+    s.send(2).now().unwrap();
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    s.send(3).now().unwrap();
+    // Full with one held back, so that is published for the Receiver.
+    assert_eq!(full(4), s.send(4).now());
+    assert_eq!(Ok(Some(2)), r.receive().now());
+    assert_eq!(Ok(Some(3)), r.receive().now());
+}
+
+#[test]
+fn flush_drop() {
+    let (mut s, mut r) = spsc::<i32>(4);
+    s.set_flush_threshold(4);
+    s.send(1).now().unwrap();
+    drop(s);
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn flush_closed() {
+    let (mut s, r) = spsc::<i32>(4);
+    s.set_flush_threshold(2);
+    s.send(1).now().unwrap();
+    drop(r);
+    assert_eq!(closed(2), s.send(2).now());
+    assert_eq!(Err(Closed), s.flush());
+    assert_eq!(Some(1), s.reclaim());
+    assert_eq!(None, s.reclaim());
+}
+
+fn laps<C: Capacity>(mut s: Sender<u32, DefaultNotify, C>, mut r: Receiver<u32, DefaultNotify, C>) {
+    let cap = s.capacity() as usize;
+    let mut next = 0;
+    for i in 0..cap * 5 {
+        s.send(i as u32).now().unwrap();
+        if i % 3 == 2 || s.is_full() {
+            while let Ok(Some(v)) = r.receive().now() {
+                assert_eq!(next, v);
+                next += 1;
+            }
+        }
+    }
+    drop(s);
+    while let Ok(Some(v)) = r.receive().now() {
+        assert_eq!(next, v);
+        next += 1;
+    }
+    assert_eq!(cap as u32 * 5, next);
+}
+
+#[test]
+fn wrap_around() {
+    // Both power of two capacities and the rest, over several laps.
+    for cap in 1..=9u32 {
+        let (s, r) = spsc::<u32>(cap as _);
+        laps(s, r);
+    }
+}
+
+#[test]
+fn pow2_capacity() {
+    for cap in [1, 2, 4, 8, 64] {
+        let (s, r) = spsc_pow2::<u32>(cap);
+        assert_eq!(cap, s.capacity());
+        laps(s, r);
+    }
+}
+
+#[test]
+#[should_panic]
+fn pow2_capacity_odd() { let _ = spsc_pow2::<u32>(6); }
+
+#[test]
+fn const_capacity() {
+    let (mut s, mut r) = spsc_const::<u32, 3>();
+    assert_eq!(3, s.capacity());
+    for lap in 0..4 {
+        for i in 0..3 { s.send(lap * 3 + i).now().unwrap(); }
+        assert_eq!(full(99), s.send(99).now());
+        for i in 0..3 { assert_eq!(Ok(Some(lap * 3 + i)), r.receive().now()); }
+    }
+    s.send(1).now().unwrap();
+    drop(r);
+    assert_eq!(Some(1), s.reclaim());
+}
+
+// One more than a 32-bit state word allows.
+#[cfg(all(target_pointer_width="32", not(feature="wide")))]
+#[test]
+#[should_panic]
+fn too_deep() { spsc::<u8>(1 << 14); }
+
+// Deeper than the 2^14 a 32-bit state word allows.
+#[cfg(any(target_pointer_width="64", feature="wide"))]
+#[test]
+fn deep() {
+    let (mut s, mut r) = spsc::<u8>(1 << 20);
+    for i in 0..1 << 20 { s.send(i as u8).now().unwrap(); }
+    assert!(s.is_full());
+    for i in 0..1 << 20 { assert_eq!(Ok(Some(i as u8)), r.receive().now()); }
+}
@@ -0,0 +1,257 @@
+#![cfg(feature="shm")]
+use async_spsc::*;
+use async_spsc::shm::*;
+use std::env;
+use std::ffi::{CStr, CString};
+use std::future::Future;
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Names are global, so we make them unique to this process.
+fn name(test: &str) -> CString {
+    CString::new(format!("/async-spsc-{}-{}", std::process::id(), test)).unwrap()
+}
+
+#[test]
+fn create_open() {
+    let name = name("create_open");
+    let shm = Shm::<u64>::create(&name, 2).unwrap();
+    let peer = unsafe { Shm::<u64>::open(&name) }.unwrap();
+    Shm::<u64>::unlink(&name).unwrap();
+    assert_eq!(2, peer.capacity());
+    let mut s = shm.sender().unwrap();
+    let mut r = peer.receiver().unwrap();
+    assert_eq!(Ok(None), r.receive().now());
+    assert_eq!(Ok(()), s.send(42).now());
+    assert_eq!(Ok(()), s.send(420).now());
+    assert_eq!(Err(SendError { kind: SendErrorKind::Full, value: 7 }), s.send(7).now());
+    assert_eq!(Ok(Some(42)), r.receive().now());
+    assert_eq!(Ok(Some(420)), r.receive().now());
+    drop(s);
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn create_exists() {
+    let name = name("create_exists");
+    let _shm = Shm::<u64>::create(&name, 1).unwrap();
+    assert_eq!(ShmError::Os(libc::EEXIST), Shm::<u64>::create(&name, 1).unwrap_err());
+    Shm::<u64>::unlink(&name).unwrap();
+}
+
+#[test]
+fn open_missing() {
+    let name = name("open_missing");
+    assert_eq!(ShmError::Os(libc::ENOENT), unsafe { Shm::<u64>::open(&name) }.unwrap_err());
+}
+
+#[test]
+fn open_wrong_layout() {
+    let name = name("open_wrong_layout");
+    let _shm = Shm::<u64>::create(&name, 1).unwrap();
+    assert_eq!(ShmError::Layout, unsafe { Shm::<u32>::open(&name) }.unwrap_err());
+    Shm::<u64>::unlink(&name).unwrap();
+}
+
+#[test]
+fn claim_twice() {
+    let name = name("claim_twice");
+    let shm = Shm::<u64>::create(&name, 1).unwrap();
+    let peer = unsafe { Shm::<u64>::open(&name) }.unwrap();
+    Shm::<u64>::unlink(&name).unwrap();
+    let _s = shm.sender().unwrap();
+    assert_eq!(Some(ShmError::Claimed), peer.sender().err());
+}
+
+#[test]
+fn wait_threads() {
+    let (shm, fd) = Shm::<u64>::memfd(&name("wait_threads"), 4).unwrap();
+    let peer = unsafe { Shm::<u64>::from_fd(fd) }.unwrap();
+    unsafe { libc::close(fd) };
+    let mut s = shm.sender().unwrap();
+    let t = thread::spawn(move || {
+        for i in 0..10000 { assert_eq!(Ok(()), s.send(i).wait()); }
+    });
+    let mut r = peer.receiver().unwrap();
+    for i in 0..10000 { assert_eq!(Ok(i), r.receive().wait()); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().wait());
+}
+
+#[test]
+fn wait_close() {
+    let (shm, fd) = Shm::<u64>::memfd(&name("wait_close"), 1).unwrap();
+    let peer = unsafe { Shm::<u64>::from_fd(fd) }.unwrap();
+    unsafe { libc::close(fd) };
+    let mut s = shm.sender().unwrap();
+    let r = peer.receiver().unwrap();
+    assert_eq!(Ok(()), s.send(42).wait());
+    let t = thread::spawn(move || s.send(420).wait());
+    thread::sleep(std::time::Duration::from_millis(50));
+    drop(r);
+    assert_eq!(Err(SendError { kind: SendErrorKind::Closed, value: 420 }), t.join().unwrap());
+}
+
+// A child process claims the other side of a channel by running the
+// `child` test. Forking alone would not do: the harness is
+// multithreaded, so a forked child must not allocate, and claiming a
+// side does.
+const ROLE: &str = "ASYNC_SPSC_SHM_ROLE";
+const NAME: &str = "ASYNC_SPSC_SHM_NAME";
+
+// Starts a child which plays `role` on the channel called `name`.
+fn spawn(role: &str, name: &CStr) -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args(["child", "--exact", "--test-threads=1", "--nocapture"])
+        .env(ROLE, role)
+        .env(NAME, name.to_str().unwrap())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap()
+}
+
+fn kill(mut child: Child) {
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+// Does nothing unless run by spawn(). Sleeps until killed once done.
+#[test]
+fn child() {
+    let (role, name) = match (env::var(ROLE), env::var(NAME)) {
+        (Ok(role), Ok(name)) => (role, CString::new(name).unwrap()),
+        _ => return,
+    };
+    let shm = unsafe { Shm::<u64>::open(&name) }.unwrap();
+    match role.as_str() {
+        "sender" => {
+            let mut s = shm.sender().unwrap();
+            s.send(42).now().unwrap();
+            s.send(420).now().unwrap();
+            std::mem::forget(s);
+            // Let the parent know both are sent.
+            unsafe { libc::raise(libc::SIGSTOP) };
+        }
+        "receiver" => {
+            let mut r = shm.receiver().unwrap();
+            assert_eq!(Ok(1), r.receive().wait());
+            std::mem::forget(r);
+        }
+        _ => unreachable!(),
+    }
+    loop { unsafe { libc::pause(); } }
+}
+
+#[test]
+fn sender_crash() {
+    let name = name("sender_crash");
+    let peer = Shm::<u64>::create(&name, 4).unwrap();
+    let child = spawn("sender", &name);
+    let mut r = peer.receiver().unwrap();
+    assert_eq!(Ok(42), r.receive().wait());
+    unsafe { libc::waitpid(child.id() as libc::pid_t, std::ptr::null_mut(), libc::WUNTRACED) };
+    kill(child);
+    Shm::<u64>::unlink(&name).unwrap();
+    // What was sent before the crash is still there.
+    assert_eq!(Ok(420), r.receive().wait());
+    assert_eq!(Err(Closed), r.receive().wait());
+}
+
+#[test]
+fn receiver_crash() {
+    let name = name("receiver_crash");
+    let peer = Shm::<u64>::create(&name, 2).unwrap();
+    let child = spawn("receiver", &name);
+    let mut s = peer.sender().unwrap();
+    assert_eq!(Ok(()), s.send(1).wait());
+    assert_eq!(Ok(()), s.send(2).wait());
+    // Only completes once the child has received.
+    assert_eq!(Ok(()), s.send(3).wait());
+    kill(child);
+    Shm::<u64>::unlink(&name).unwrap();
+    assert_eq!(Err(SendError { kind: SendErrorKind::Closed, value: 4 }), s.send(4).wait());
+    assert_eq!(Some(2), s.reclaim());
+    assert_eq!(Some(3), s.reclaim());
+    assert_eq!(None, s.reclaim());
+}
+
+#[test]
+fn heartbeat_timeout() {
+    let (shm, fd) = Shm::<u64>::memfd(&name("heartbeat_timeout"), 1).unwrap();
+    let peer = unsafe { Shm::<u64>::from_fd(fd) }.unwrap();
+    unsafe { libc::close(fd) };
+    // A Sender that is alive but never waits.
+    let _s = shm.sender().unwrap();
+    let mut r = peer.heartbeat_timeout(Duration::from_millis(250)).receiver().unwrap();
+    let start = Instant::now();
+    assert_eq!(Err(Closed), r.receive().wait());
+    assert!(start.elapsed() >= Duration::from_millis(250));
+}
+
+// Wakes the test thread, counting how often.
+struct Unpark(thread::Thread, AtomicUsize);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) { self.wake_by_ref() }
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        self.0.unpark();
+    }
+}
+
+#[test]
+fn await_threads() {
+    let (shm, fd) = Shm::<u64>::memfd(&name("await_threads"), 1).unwrap();
+    let peer = unsafe { Shm::<u64>::from_fd(fd) }.unwrap();
+    unsafe { libc::close(fd) };
+    let mut s = shm.sender().unwrap();
+    let mut r = peer.receiver().unwrap();
+    let t = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(Ok(()), s.send(42).wait());
+    });
+    let unpark = Arc::new(Unpark(thread::current(), AtomicUsize::new(0)));
+    let waker = Waker::from(unpark.clone());
+    let mut ctx = Context::from_waker(&waker);
+    let mut f = Box::pin(r.receive());
+    let ret = loop {
+        if let Poll::Ready(ret) = f.as_mut().poll(&mut ctx) { break ret; }
+        thread::park();
+    };
+    assert_eq!(Ok(42), ret);
+    // Woken by the peer (and perhaps a heartbeat), not polled in a loop.
+    assert!(unpark.1.load(Ordering::Relaxed) < 10);
+    t.join().unwrap();
+}
+
+#[test]
+fn heartbeat_busy() {
+    let (shm, fd) = Shm::<u64>::memfd(&name("heartbeat_busy"), 4).unwrap();
+    let peer = unsafe { Shm::<u64>::from_fd(fd) }.unwrap();
+    unsafe { libc::close(fd) };
+    // A Sender that is busy, but never waits.
+    let mut s = shm.sender().unwrap();
+    let t = thread::spawn(move || {
+        let start = Instant::now();
+        let mut i = 0;
+        while start.elapsed() < Duration::from_millis(600) {
+            thread::sleep(Duration::from_millis(1));
+            match s.send(i).now() {
+                Ok(()) => i += 1,
+                Err(e) => assert_eq!(SendErrorKind::Full, e.kind),
+            }
+        }
+        i
+    });
+    let mut r = peer.heartbeat_timeout(Duration::from_millis(250)).receiver().unwrap();
+    let mut i = 0;
+    while let Ok(v) = r.receive().wait() {
+        assert_eq!(i, v);
+        i += 1;
+    }
+    assert_eq!(t.join().unwrap(), i);
+}
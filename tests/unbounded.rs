@@ -0,0 +1,136 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+mod common;
+use common::*;
+
+#[test]
+fn grow() {
+    let (mut s, mut r) = unbounded_spsc::<usize>(3);
+    assert_eq!(3, s.capacity());
+    for i in 0..20 { assert_eq!(Ok(()), s.send(i).now()); }
+    for i in 0..20 { assert_eq!(Ok(Some(i)), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+    // Interleaved, across several more segments.
+    let mut next = 20;
+    for i in 20..100 {
+        s.send(i).now().unwrap();
+        if i % 7 == 0 {
+            while let Ok(Some(v)) = r.receive().now() {
+                assert_eq!(next, v);
+                next += 1;
+            }
+        }
+    }
+    drop(s);
+    while let Ok(Some(v)) = r.receive().now() {
+        assert_eq!(next, v);
+        next += 1;
+    }
+    assert_eq!(100, next);
+    assert_eq!(Err(Closed), r.receive().now());
+}
+
+#[test]
+fn drops() {
+    for receiver_first in [false, true] {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let (mut s, mut r) = unbounded_spsc::<Drops>(2);
+        for _ in 0..7 { assert!(s.send(Drops(drops.clone())).now().is_ok()); }
+        drop(r.receive().now());
+        assert_eq!(1, drops.load(Ordering::Relaxed));
+        if receiver_first {
+            drop(r);
+            // Those in every segment the Sender sealed are gone.
+            assert_eq!(6, drops.load(Ordering::Relaxed));
+            assert!(s.send(Drops(drops.clone())).now().is_err());
+            assert_eq!(7, drops.load(Ordering::Relaxed));
+            drop(s);
+            assert_eq!(8, drops.load(Ordering::Relaxed));
+        } else {
+            drop(s);
+            assert_eq!(1, drops.load(Ordering::Relaxed));
+            drop(r);
+            assert_eq!(7, drops.load(Ordering::Relaxed));
+        }
+    }
+}
+
+#[test]
+fn receiver_gone() {
+    // The Receiver leaves before the Sender fills its first segment.
+    let (mut s, r) = unbounded_spsc::<i32>(2);
+    s.send(1).now().unwrap();
+    drop(r);
+    s.send(2).now().unwrap_err();
+    // And while the Sender is a segment ahead.
+    let (mut s, mut r) = unbounded_spsc::<i32>(2);
+    for i in 0..5 { s.send(i).now().unwrap(); }
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    drop(r);
+    assert_eq!(SendErrorKind::Closed, s.send(5).now().unwrap_err().kind);
+}
+
+#[test]
+fn async_receive() {
+    let (mut s, mut r) = unbounded_spsc::<i32>(1);
+    s.send(1).now().unwrap();
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    {
+        wookie!(r2: r.receive());
+        assert_eq!(Poll::Pending, r2.poll());
+        // Fills the segment, then seals it for the next.
+        s.send(2).now().unwrap();
+        s.send(3).now().unwrap();
+        r2.stats().assert(2, 1, 1);
+        assert_eq!(Poll::Ready(Ok(2)), r2.poll());
+    }
+    {
+        wookie!(r2: r.receive());
+        assert_eq!(Poll::Ready(Ok(3)), r2.poll());
+    }
+    wookie!(r2: r.receive());
+    assert_eq!(Poll::Pending, r2.poll());
+    // Sending never waits.
+    wookie!(s2: s.send(4));
+    assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    assert_eq!(Poll::Ready(Ok(4)), r2.poll());
+}
+
+#[test]
+fn wait_len() {
+    let (mut s, mut r) = unbounded_spsc::<i32>(2);
+    for i in 0..3 { s.send(i).now().unwrap(); }
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    {
+        // The rest of the first segment is all that is in reach.
+        wookie!(w: r.wait_len(2));
+        assert_eq!(Poll::Ready(Ok(1)), w.poll());
+    }
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    wookie!(w: r.wait_len(2));
+    assert_eq!(Poll::Pending, w.poll());
+    s.send(3).now().unwrap();
+    assert_eq!(Poll::Ready(Ok(2)), w.poll());
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 100_000;
+    let drops = Arc::new(AtomicUsize::new(0));
+    let (mut s, mut r) = unbounded_spsc::<(usize, Drops)>(16);
+    let d = drops.clone();
+    let t = std::thread::spawn(move || {
+        for i in 0..COUNT { assert!(s.send((i, Drops(d.clone()))).now().is_ok()); }
+    });
+    let mut next = 0;
+    receive_all(|| r.receive().now(), |(v, _)| {
+        assert_eq!(next, v);
+        next += 1;
+    });
+    t.join().unwrap();
+    assert_eq!(COUNT, next);
+    assert_eq!(COUNT, drops.load(Ordering::Relaxed));
+}
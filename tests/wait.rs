@@ -0,0 +1,60 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::time::Duration;
+
+#[test]
+fn spin_receive() {
+    let (mut s, mut r) = spsc::<i32>(1);
+    r.set_wait_strategy(WaitStrategy::Spin(u32::MAX));
+    let t = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        s.send(42).now().unwrap();
+        s
+    });
+    wookie!(r2: r.receive());
+    // We never had to register a waker.
+    assert_eq!(Poll::Ready(Ok(42)), r2.poll());
+    r2.stats().assert(0, 0, 0);
+    t.join().unwrap();
+}
+
+#[test]
+fn spin_send() {
+    let (mut s, mut r) = spsc::<i32>(1);
+    s.send(1).now().unwrap();
+    s.set_wait_strategy(WaitStrategy::Spin(u32::MAX));
+    let t = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(Ok(Some(1)), r.receive().now());
+        r
+    });
+    wookie!(s2: s.send(2));
+    assert_eq!(Poll::Ready(Ok(())), s2.poll());
+    s2.stats().assert(0, 0, 0);
+    let mut r = t.join().unwrap();
+    assert_eq!(Ok(Some(2)), r.receive().now());
+}
+
+#[test]
+fn spin_gives_up() {
+    let (_s, mut r) = spsc::<i32>(1);
+    r.set_wait_strategy(WaitStrategy::Spin(100));
+    wookie!(r2: r.receive());
+    assert_eq!(Poll::Pending, r2.poll());
+    r2.stats().assert(2, 0, 0);
+}
+
+#[cfg(feature="std")]
+#[test]
+fn spin_yield_wait() {
+    let (mut s, mut r) = spsc_with::<usize, _>(1, Park::default());
+    s.set_wait_strategy(WaitStrategy::SpinYield { spins: 100, yields: 10 });
+    r.set_wait_strategy(WaitStrategy::SpinYield { spins: 100, yields: 10 });
+    let t = std::thread::spawn(move || {
+        for i in 0..10000 { s.send(i).wait().unwrap(); }
+    });
+    for i in 0..10000 { assert_eq!(Ok(i), r.receive().wait()); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), r.receive().wait());
+}
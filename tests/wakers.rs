@@ -0,0 +1,283 @@
+#![cfg(feature="async")]
+use async_spsc::*;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Wake;
+use std::thread;
+mod common;
+use common::*;
+
+#[derive(Default)]
+struct Count(AtomicUsize);
+
+impl Wake for Count {
+    fn wake(self: Arc<Self>) { self.0.fetch_add(1, Ordering::Relaxed); }
+}
+
+fn stress(capacity: u32) {
+    #[cfg(any(target_pointer_width="64", feature="wide"))]
+    let (mut s, mut r) = spsc::<u32>(capacity);
+    // The capacity is only a u16 here.
+    #[cfg(all(target_pointer_width="32", not(feature="wide")))]
+    let (mut s, mut r) = spsc::<u32>(capacity as u16);
+    let t = thread::spawn(move || {
+        for i in 0..100_000 { block_on(s.send(i)).unwrap(); }
+    });
+    for i in 0..100_000 { assert_eq!(Ok(i), block_on(r.receive())); }
+    t.join().unwrap();
+    assert_eq!(Err(Closed), block_on(r.receive()));
+}
+
+#[test]
+fn stress_1() { stress(1) }
+
+#[test]
+fn stress_2() { stress(2) }
+
+// The most a 32-bit state word allows.
+#[cfg(all(target_pointer_width="32", not(feature="wide")))]
+#[test]
+fn stress_max() { stress((1 << 14) - 1) }
+
+#[test]
+fn receiver_drop_clears_slot() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (_s, mut r) = spsc::<i32>(1);
+    // With no future to clean up after it, this leaves the waker in
+    // the slot.
+    assert_eq!(Poll::Pending, r.poll_recv(&mut Context::from_waker(&waker)));
+    assert_eq!(3, Arc::strong_count(&count));
+    drop(r);
+    assert_eq!(2, Arc::strong_count(&count));
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+}
+
+#[test]
+fn receiving_drop_clears_slot() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(1);
+    {
+        let mut fut = r.receive();
+        let pin = unsafe { Pin::new_unchecked(&mut fut) };
+        assert_eq!(Poll::Pending, pin.poll(&mut Context::from_waker(&waker)));
+    }
+    assert_eq!(2, Arc::strong_count(&count));
+    s.send(1).now().unwrap();
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+    assert_eq!(Ok(Some(1)), r.receive().now());
+}
+
+#[test]
+fn into_recv_drop_clears_slot() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, r) = spsc::<i32>(1);
+    let mut fut = r.into_recv();
+    let pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.poll(&mut Context::from_waker(&waker)).map(|(_, ret)| ret));
+    drop(fut);
+    assert_eq!(2, Arc::strong_count(&count));
+    assert!(s.send(1).now().is_err());
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+}
+
+#[test]
+fn sender_close_wakes_receiver() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (s, mut r) = spsc::<i32>(1);
+    let mut fut = r.receive();
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    drop(s);
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Err(Closed)), pin.poll(&mut Context::from_waker(&waker)));
+}
+
+#[test]
+fn receiver_close_wakes_sender() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, r) = spsc::<i32>(1);
+    s.send(1).now().unwrap();
+    let mut fut = s.send(2);
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    drop(r);
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    let ret = pin.poll(&mut Context::from_waker(&waker));
+    assert_eq!(Poll::Ready(Err(SendError { kind: SendErrorKind::Closed, value: 2 })), ret);
+}
+
+#[test]
+fn min_batch() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(8);
+    r.set_min_batch(4);
+    let mut fut = r.receive();
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    for i in 0..3 { s.send(i).now().unwrap(); }
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+    s.send(3).now().unwrap();
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Ok(0)), pin.poll(&mut Context::from_waker(&waker)));
+}
+
+#[test]
+fn min_batch_close() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(8);
+    r.set_min_batch(4);
+    let mut fut = r.receive();
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    s.send(0).now().unwrap();
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+    drop(s);
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Ok(0)), pin.poll(&mut Context::from_waker(&waker)));
+}
+
+#[test]
+fn min_space() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(4);
+    s.set_min_space(2);
+    for i in 0..4 { s.send(i).now().unwrap(); }
+    let mut fut = s.send(4);
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Ok(())), pin.poll(&mut Context::from_waker(&waker)));
+}
+
+#[test]
+#[should_panic]
+fn min_batch_too_big() {
+    let (_s, mut r) = spsc::<i32>(4);
+    r.set_min_batch(5);
+}
+
+#[test]
+fn wait_len() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(4);
+    {
+        let mut fut = r.wait_len(3);
+        let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+        assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+        s.send(0).now().unwrap();
+        s.send(1).now().unwrap();
+        assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+        s.send(2).now().unwrap();
+        assert_eq!(Poll::Ready(Ok(3)), pin.poll(&mut Context::from_waker(&waker)));
+    }
+    // Nothing was consumed.
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    assert_eq!(Ok(2), block_on(r.wait_len(2)));
+    drop(s);
+    assert_eq!(Ok(2), block_on(r.wait_len(1)));
+    assert_eq!(Err(Closed), block_on(r.wait_len(3)));
+}
+
+// Each side waits for the other to do something it won't, without
+// either waking the other.
+#[test]
+fn both_waiting() {
+    let sender = Arc::new(Count::default());
+    let receiver = Arc::new(Count::default());
+    let (sender_waker, receiver_waker) = (Waker::from(sender.clone()), Waker::from(receiver.clone()));
+    let (mut s, mut r) = spsc::<i32>(4);
+    s.send(0).now().unwrap();
+    s.send(1).now().unwrap();
+    let mut space = s.wait_space(4);
+    let mut space = unsafe { Pin::new_unchecked(&mut space) };
+    {
+        let mut len = r.wait_len(3);
+        let mut len = unsafe { Pin::new_unchecked(&mut len) };
+        for _ in 0..3 {
+            assert_eq!(Poll::Pending, len.as_mut().poll(&mut Context::from_waker(&receiver_waker)));
+            assert_eq!(Poll::Pending, space.as_mut().poll(&mut Context::from_waker(&sender_waker)));
+        }
+        assert_eq!(0, sender.0.load(Ordering::Relaxed));
+        assert_eq!(0, receiver.0.load(Ordering::Relaxed));
+    }
+    // The Sender's waker is still registered, and its future's copy
+    // kept, but dropping the Receiver's future took its own away.
+    assert_eq!(4, Arc::strong_count(&sender));
+    assert_eq!(2, Arc::strong_count(&receiver));
+    assert_eq!(Ok(Some(0)), r.receive().now());
+    assert_eq!(Ok(Some(1)), r.receive().now());
+    assert_eq!(1, sender.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Ok(4)), space.poll(&mut Context::from_waker(&sender_waker)));
+}
+
+#[test]
+fn wait_len_close() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(4);
+    s.send(0).now().unwrap();
+    let mut fut = r.wait_len(2);
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    drop(s);
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Err(Closed)), pin.poll(&mut Context::from_waker(&waker)));
+}
+
+#[test]
+fn wait_space() {
+    let (mut s, mut r) = spsc::<u32>(4);
+    for i in 0..4 { s.send(i).now().unwrap(); }
+    let t = thread::spawn(move || {
+        for i in 0..3 {
+            thread::sleep(std::time::Duration::from_millis(5));
+            assert_eq!(Ok(Some(i)), r.receive().now());
+        }
+        r
+    });
+    assert!(block_on(s.wait_space(3)).unwrap() >= 3);
+    let r = t.join().unwrap();
+    // Nothing was sent.
+    assert_eq!(3, s.space());
+    drop(r);
+    assert_eq!(Err(Closed), block_on(s.wait_space(1)));
+}
+
+#[test]
+#[should_panic]
+fn wait_space_too_big() {
+    let (mut s, _r) = spsc::<i32>(4);
+    drop(s.wait_space(5));
+}
+
+#[test]
+fn flush_wakes() {
+    let count = Arc::new(Count::default());
+    let waker = Waker::from(count.clone());
+    let (mut s, mut r) = spsc::<i32>(8);
+    s.set_flush_threshold(4);
+    r.set_min_batch(4);
+    let mut fut = r.receive();
+    let mut pin = unsafe { Pin::new_unchecked(&mut fut) };
+    assert_eq!(Poll::Pending, pin.as_mut().poll(&mut Context::from_waker(&waker)));
+    s.send(0).now().unwrap();
+    assert_eq!(0, count.0.load(Ordering::Relaxed));
+    s.flush().unwrap();
+    assert_eq!(1, count.0.load(Ordering::Relaxed));
+    assert_eq!(Poll::Ready(Ok(0)), pin.poll(&mut Context::from_waker(&waker)));
+}
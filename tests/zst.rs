@@ -0,0 +1,141 @@
+use async_spsc::*;
+use wookie::*;
+use core::task::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+mod common;
+use common::*;
+
+// Counts how many times one has been dropped, with no room for the
+// count itself.
+static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+struct Tick;
+
+impl Drop for Tick {
+    fn drop(&mut self) { DROPS.fetch_add(1, Ordering::Relaxed); }
+}
+
+#[test]
+fn saturate() {
+    // Capped at the most the state can count, which is too large for
+    // any other message type.
+    let (mut s, mut r) = spsc::<()>(!0);
+    #[cfg(any(target_pointer_width="64", feature="wide"))]
+    assert_eq!((1 << 31) - 1, s.capacity());
+    #[cfg(all(target_pointer_width="32", not(feature="wide")))]
+    assert_eq!((1 << 15) - 1, s.capacity());
+    let count = (s.capacity() as usize).min(1 << 20);
+    for _ in 0..count { s.send(()).now().unwrap(); }
+    for _ in 0..count { assert_eq!(Ok(Some(())), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+}
+
+#[test]
+fn sender_is_empty() {
+    let (mut s, _r) = spsc::<i32>(2);
+    assert!(s.is_empty() && !s.is_full());
+    for i in 0..2 { s.send(i).now().unwrap(); }
+    assert!(!s.is_empty() && s.is_full());
+    // The ring positions go round a wider ring for zero-sized messages.
+    let (mut s, _r) = spsc::<()>(2);
+    assert!(s.is_empty() && !s.is_full());
+    for _ in 0..s.capacity() { s.send(()).now().unwrap(); }
+    assert!(!s.is_empty() && s.is_full());
+}
+
+// Beyond the usual maximum for the width, wrapping around many times.
+#[test]
+fn beyond_max_capacity() {
+    // An `AtomicU16` could otherwise hold at most 63.
+    let (mut s, mut r) = spsc_compact::<(), u8>(255);
+    assert_eq!(127, s.capacity());
+    let mut taken = 127;
+    for round in 0..20 {
+        let mut sent = 0;
+        while s.send(()).now().is_ok() { sent += 1; }
+        // As many as were taken fit back in.
+        assert_eq!(taken, sent);
+        // Leave some behind, so the positions wrap past each other.
+        taken = 100 + round;
+        for _ in 0..taken { assert_eq!(Ok(Some(())), r.receive().now()); }
+    }
+    let (mut s, mut r) = spsc_compact::<(), u16>(20_000);
+    for _ in 0..20_000 { s.send(()).now().unwrap(); }
+    assert_eq!(SendErrorKind::Full, s.send(()).now().unwrap_err().kind);
+    for _ in 0..20_000 { assert_eq!(Ok(Some(())), r.receive().now()); }
+    assert_eq!(Ok(None), r.receive().now());
+}
+
+#[test]
+fn beyond_max_capacity_const() {
+    let (mut s, mut r) = spsc_const::<(), 20_000>();
+    assert_eq!(20_000, s.capacity());
+    for _ in 0..20_000 { s.send(()).now().unwrap(); }
+    assert!(s.send(()).now().is_err());
+    for _ in 0..20_000 { assert_eq!(Ok(Some(())), r.receive().now()); }
+    #[cfg(any(target_pointer_width="64", feature="wide"))]
+    assert_eq!(1 << 30, spsc_const::<(), { 1 << 30 }>().0.capacity());
+}
+
+#[test]
+#[should_panic]
+fn not_zst() { spsc::<u8>(!0); }
+
+#[test]
+fn full() {
+    let (mut s, mut r) = spsc::<()>(3);
+    for _ in 0..3 { s.send(()).now().unwrap(); }
+    assert_eq!(SendErrorKind::Full, s.send(()).now().unwrap_err().kind);
+    assert_eq!(Ok(Some(())), r.receive().now());
+    s.send(()).now().unwrap();
+}
+
+#[test]
+fn drops() {
+    // Only one test counts drops, as they share the count.
+    for receiver_first in [false, true] {
+        DROPS.store(0, Ordering::Relaxed);
+        let (mut s, mut r) = spsc::<Tick>(1000);
+        for _ in 0..700 { assert!(s.send(Tick).now().is_ok()); }
+        for _ in 0..200 { drop(r.receive().now()); }
+        assert_eq!(200, DROPS.load(Ordering::Relaxed));
+        if receiver_first { drop(r); drop(s); } else { drop(s); drop(r); }
+        assert_eq!(700, DROPS.load(Ordering::Relaxed));
+    }
+    // More than 63 left in flight, after the positions have wrapped.
+    DROPS.store(0, Ordering::Relaxed);
+    let (mut s, mut r) = spsc_compact::<Tick, u8>(120);
+    for _ in 0..120 { assert!(s.send(Tick).now().is_ok()); }
+    for _ in 0..100 { drop(r.receive().now()); }
+    for _ in 0..100 { assert!(s.send(Tick).now().is_ok()); }
+    drop(s);
+    drop(r);
+    assert_eq!(220, DROPS.load(Ordering::Relaxed));
+}
+
+#[test]
+fn async_count() {
+    let (mut s, mut r) = spsc::<()>(1 << 12);
+    {
+        wookie!(w: r.wait_len(100));
+        assert_eq!(Poll::Pending, w.poll());
+        for _ in 0..99 { s.send(()).now().unwrap(); }
+        assert_eq!(Poll::Pending, w.poll());
+        s.send(()).now().unwrap();
+        assert_eq!(Poll::Ready(Ok(100)), w.poll());
+    }
+    for _ in 0..100 { assert_eq!(Ok(Some(())), r.receive().now()); }
+}
+
+#[test]
+fn threads() {
+    const COUNT: usize = 1_000_000;
+    let (mut s, mut r) = spsc::<()>(!0);
+    let t = std::thread::spawn(move || {
+        for _ in 0..COUNT { send_or_yield(|v| s.send(v).now(), ()).unwrap(); }
+    });
+    let mut received = 0;
+    receive_all(|| r.receive().now(), |()| received += 1);
+    t.join().unwrap();
+    assert_eq!(COUNT, received);
+}